@@ -0,0 +1,113 @@
+use fondabots_lib::tools::{basicize, get_object};
+use fondabots_lib::{Bot, ErrType, Object};
+use poise::serenity_prelude as serenity;
+use poise::Context;
+use serenity::all::AutocompleteChoice;
+use strum::IntoEnumIterator;
+
+use crate::ecrit::fields::{Status, Type};
+use crate::ecrit::navigateur::libelle;
+use crate::ecrit::requete::Tri;
+use crate::ecrit::Ecrit;
+use crate::DataType;
+
+/// Nombre maximal de propositions accepté par Discord.
+const PROPOSITIONS: usize = 25;
+
+/// Clés des requêtes de `/ulister`, proposées avant qu’une clé soit tapée.
+const CLES_REQUETE: [&str; 8] = ["statut:", "type:", "auteur:", "tag:", "nom:", "avant:", "après:", "tri:"];
+
+/// Résout le critère d’identification d’un écrit. Le critère peut être l’identifiant forum de
+/// l’écrit, ce que renvoie l’autocomplétion, ou un critère de recherche classique.
+pub async fn trouver_ecrit(ctx: &Context<'_, DataType, ErrType>, bot: &mut Bot<Ecrit>, critere: &String) -> Result<Option<u64>, ErrType> {
+    match critere.trim().parse::<u64>() {
+        Ok(id) if bot.database.contains_key(&id) => Ok(Some(id)),
+        _ => get_object(ctx, bot, critere).await
+    }
+}
+
+/// Tags existants dans la base de données, sans doublons.
+fn tags(bot: &Bot<Ecrit>) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in bot.database.values().flat_map(|ecrit| ecrit.tags.iter()) {
+        if !tags.iter().any(|connu| basicize(connu) == basicize(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    tags.sort_by_cached_key(|tag| basicize(tag));
+    tags
+}
+
+/// Écrits dont le nom ou l’auteur contient la saisie, ou dont l’identifiant forum commence par elle.
+/// La valeur proposée est l’identifiant, reconnu par [`trouver_ecrit`].
+pub async fn ecrits(ctx: Context<'_, DataType, ErrType>, saisie: &str) -> Vec<AutocompleteChoice> {
+    let bot = ctx.data().lock().await;
+    let saisie_basique = basicize(saisie);
+    let ecrits = bot.database.iter().filter(|(id, ecrit)| {
+        basicize(&ecrit.nom).contains(&saisie_basique)
+            || basicize(&ecrit.auteur).contains(&saisie_basique)
+            || id.to_string().starts_with(saisie.trim())
+    }).collect();
+    Tri::Date.trier(ecrits).into_iter().take(PROPOSITIONS)
+        .map(|(id, ecrit)| AutocompleteChoice::new(
+            libelle(&format!("{} · {} ({})", ecrit.get_name(), ecrit.auteur, ecrit.status)), id.to_string()))
+        .collect()
+}
+
+/// Tags existants contenant la saisie.
+pub async fn tags_existants(ctx: Context<'_, DataType, ErrType>, saisie: &str) -> Vec<String> {
+    let bot = ctx.data().lock().await;
+    let saisie = basicize(saisie);
+    tags(&bot).into_iter().filter(|tag| basicize(tag).contains(&saisie)).take(PROPOSITIONS).collect()
+}
+
+/// Auteurs des écrits de la base de données dont le nom contient la saisie.
+pub async fn auteurs(ctx: Context<'_, DataType, ErrType>, saisie: &str) -> Vec<String> {
+    let bot = ctx.data().lock().await;
+    let saisie = basicize(saisie);
+    Ecrit::liste_auteurs(&bot.database).into_iter()
+        .filter(|auteur| basicize(auteur).contains(&saisie) && auteur.chars().count() <= 100)
+        .take(PROPOSITIONS)
+        .cloned()
+        .collect()
+}
+
+/// Complète le dernier terme d’une requête de `/ulister` : la clé, puis les statuts, types, tags,
+/// auteurs ou tris existants.
+pub async fn requete(ctx: Context<'_, DataType, ErrType>, saisie: &str) -> Vec<String> {
+    let (debut, terme) = saisie.rsplit_once(' ')
+        .map_or(("", saisie), |(debut, terme)| (debut, terme));
+    let debut = if debut.is_empty() { String::new() } else { format!("{debut} ") };
+    let negation = if terme.starts_with('-') { "-" } else { "" };
+    let terme = terme.trim_start_matches('-');
+
+    let propositions: Vec<String> = match terme.split_once(':') {
+        None => {
+            let terme = basicize(terme);
+            CLES_REQUETE.iter().filter(|cle| basicize(cle.trim_end_matches(':')).starts_with(&terme))
+                .map(|cle| format!("{debut}{negation}{cle}")).collect()
+        }
+        Some((cle, valeurs)) => {
+            /* Les tags exigés ensemble sont séparés par des `+`. */
+            let (precedentes, valeur) = valeurs.rsplit_once([',', '+'])
+                .map_or((String::new(), valeurs), |(precedentes, valeur)| (valeurs[..precedentes.len() + 1].to_string(), valeur));
+            let valeurs_possibles: Vec<String> = match basicize(cle).as_str() {
+                "statut" | "statuts" => Status::iter().map(|status| status.to_string()).collect(),
+                "type" | "types" => Type::iter().map(|type_| type_.to_string()).collect(),
+                "tag" | "tags" => tags(&*ctx.data().lock().await),
+                "auteur" | "auteurs" => Ecrit::liste_auteurs(&ctx.data().lock().await.database).into_iter().cloned().collect(),
+                "tri" => Tri::iter().map(|tri| tri.cle().to_string()).collect(),
+                _ => Vec::new()
+            };
+            let valeur = basicize(valeur.trim_matches('"'));
+            valeurs_possibles.into_iter()
+                .filter(|possible| basicize(possible).contains(&valeur))
+                .map(|possible| if possible.contains(' ') { format!("\"{possible}\"") } else { possible })
+                .map(|possible| format!("{debut}{negation}{cle}:{precedentes}{possible}"))
+                .collect()
+        }
+    };
+    /* Discord refuse les valeurs de plus de 100 caractères. */
+    propositions.into_iter().filter(|proposition| proposition.chars().count() <= 100).take(PROPOSITIONS).collect()
+}
+
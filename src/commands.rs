@@ -1,22 +1,31 @@
 use std::collections::HashMap;
-use std::str::FromStr;
 
+use chrono::{DateTime, Utc};
 use fondabots_lib::command_data::{CommandData, Permission};
 use fondabots_lib::{
-    generic_commands,
     tools,
-    tools::{alias, basicize, get_object, parse_date},
+    tools::{alias, basicize, parse_date},
     ErrType,
     Object
 };
-use poise::{Command, Context, CreateReply};
+use poise::{ChoiceParameter, Command, Context, CreateReply};
 use rand::prelude::*;
-use serenity::all::{ChannelId, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, Timestamp};
+use rss::Channel;
+use serenity::all::{Attachment, ChannelId, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, Timestamp, User};
 
 use crate::{
+    autocompletion::{self, trouver_ecrit},
+    config,
+    permissions,
     ecrit::fields::Type,
-    ecrit::fields::{Interet, Status},
-    ecrit::Ecrit,
+    ecrit::fields::{Interet, InteretType, Status},
+    ecrit::historique::{Acteur, Action},
+    ecrit::requete::{Requete, Tri},
+    ecrit::auteurs,
+    ecrit::notifications::{Destination, Preferences, TypeNotification},
+    ecrit::statistiques::{self, Periode},
+    ecrit::titre::AnalyseurTitre,
+    ecrit::{Ecrit, ModeTags},
     DataType
 };
 
@@ -32,7 +41,9 @@ pub async fn ajouter(
 ) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
     if let Some(id) = Ecrit::find_id(&url) {
-        bot.database.insert(id, Ecrit::new(nom.clone(), url, type_, status, auteur)?);
+        let mut ecrit = Ecrit::new(nom.clone(), url, type_, status, auteur)?;
+        ecrit.historiser(&Acteur::from(ctx.author()), Action::Ajout, None, None);
+        bot.database.insert(id, ecrit);
         ctx.say(format!("Écrit « {nom} » ajouté !")).await?;
         bot.log(&ctx, format!("{} a ajouté l'écrit {nom} (id: {id})", tools::user_desc(ctx.author()))).await?;
     } else {
@@ -41,14 +52,28 @@ pub async fn ajouter(
     Ok(())
 }
 
-/// Liste tous les écrits d’un certain type ou status.
+/// Liste tous les écrits d’un certain type, status ou tag.
 #[poise::command(slash_command, category = "Recherche", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
 pub async fn lister(
     ctx: Context<'_, DataType, ErrType>,
     #[description = "Status recherché"] status: Option<Status>,
-    #[description = "Type recherché"] type_: Option<Type>
+    #[description = "Type recherché"] type_: Option<Type>,
+    #[description = "Tags recherchés, séparés par des virgules"] tags: Option<String>,
+    #[description = "Combinaison des tags : l’un d’eux (par défaut), tous ou aucun"] mode_tags: Option<ModeTags>
 ) -> Result<(), ErrType> {
-    generic_commands::lister_two(ctx, status, type_).await
+    let bot = &ctx.data().lock().await;
+    let tags: Vec<String> = tags.iter().flat_map(|tags| tags.split(','))
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let mode_tags = mode_tags.unwrap_or(ModeTags::Un);
+    let ids = bot.database.iter()
+        .filter(|(_, ecrit)| status.as_ref().is_none_or(|status| ecrit.status == *status))
+        .filter(|(_, ecrit)| type_.as_ref().is_none_or(|type_| ecrit.type_ == *type_))
+        .filter(|(_, ecrit)| ecrit.correspond_tags(&tags, mode_tags))
+        .map(|(&id, _)| id)
+        .collect();
+    Ecrit::naviguer(ctx, bot, "Liste des écrits", ids, Tri::Date).await
 }
 
 /// Nettoie la base de données en supprimant les écrits abandonnés, publiés et refusés.
@@ -77,31 +102,83 @@ pub async fn nettoyer(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType
 /// Change le statut d’un écrit.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn statut(ctx: Context<'_, DataType, ErrType>,
-                    #[description = "Critère d’identification de l’écrit"] critere: String,
-                    #[description = "Nouveau statut"] statut: Status) -> Result<(), ErrType> {
-    generic_commands::change_field(ctx, critere, statut).await
+                    #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
+                    #[description = "Nouveau statut"] statut: Status,
+                    #[description = "Force un changement de statut normalement interdit (équipe critique uniquement)"] forcer: Option<bool>) -> Result<(), ErrType> {
+    let bot = &mut ctx.data().lock().await;
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
+        let roles = ctx.author_member().await.ok_or(ErrType::Generic)?.roles.clone();
+        let peut_forcer = permissions::accorde(&roles, Permission::MANAGE);
+        if forcer.unwrap_or(false) && !peut_forcer {
+            ctx.say("Seule l’équipe critique peut forcer un changement de statut.").await?;
+            return Ok(());
+        }
+        let ecrit = bot.database.get(&object_id).unwrap();
+        let ancien_statut = ecrit.status.clone();
+        let refus = match ecrit.status.transition(&statut, &ecrit.type_) {
+            Ok(permission) if permissions::accorde(&roles, permission) => None,
+            Ok(_) => Some(format!("Vous n’avez pas la permission de passer un écrit au statut {statut}.")),
+            Err(raison) => Some(raison)
+        };
+        if let Some(refus) = &refus {
+            if !forcer.unwrap_or(false) {
+                ctx.say(if peut_forcer {
+                    format!("{refus} Utilisez l’option `forcer` pour passer outre.")
+                } else {
+                    refus.clone()
+                }).await?;
+                return Ok(());
+            }
+        }
+
+        bot.archive(vec![object_id]);
+        let ecrit = bot.database.get_mut(&object_id).unwrap();
+        ecrit.changer_statut(statut.clone(), &Acteur::from(ctx.author()));
+        ctx.say(format!("Statut de l’écrit « {} » changé pour {statut}.", ecrit.get_name())).await?;
+        let ecrit = bot.database.get(&object_id).unwrap();
+        bot.log(&ctx, match refus {
+            Some(refus) => format!("{} a forcé le changement de statut de l'écrit {} (id: {object_id}) de {ancien_statut} à {statut}. Transition normalement refusée : {refus}",
+                                   tools::user_desc(ctx.author()), ecrit.get_name()),
+            None => format!("{} a changé le statut de l'écrit {} (id: {object_id}) de {ancien_statut} à {statut}.",
+                            tools::user_desc(ctx.author()), ecrit.get_name())
+        }).await?;
+        ecrit.notifier_statut(&Acteur::from(ctx.author()));
+    }
+    Ok(())
 }
 
 /// Change le type d’un écrit.
 #[poise::command(slash_command, category = "Édition", rename = "type", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn type_(ctx: Context<'_, DataType, ErrType>,
-                   #[description = "Critère d’identification de l’écrit"] critere: String,
+                   #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
                    #[description = "Nouveau type"]
                    #[rename = "type"]
                    type_: Type) -> Result<(), ErrType> {
-    generic_commands::change_field(ctx, critere, type_).await
+    let bot = &mut ctx.data().lock().await;
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
+        bot.archive(vec![object_id]);
+        let ecrit = bot.database.get_mut(&object_id).unwrap();
+        let ancien_type = ecrit.type_.clone();
+        ecrit.changer_type(type_.clone(), &Acteur::from(ctx.author()));
+        ctx.say(format!("Type de l’écrit « {} » changé pour {type_}.", ecrit.get_name())).await?;
+        let ecrit = bot.database.get(&object_id).unwrap();
+        bot.log(&ctx, format!("{} a changé le type de l'écrit {} (id: {object_id}) de {ancien_type} à {type_}.",
+                              tools::user_desc(ctx.author()), ecrit.get_name())).await?;
+    }
+    Ok(())
 }
 
 /// Ajoute ou supprime (paramètre vide) le lien Discord d'un écrit.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn lien_discord(ctx: Context<'_, DataType, ErrType>,
-                          #[description = "Critère d'identification de l'écrit"] critere: String,
+                          #[description = "Critère d'identification de l'écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
                           #[description = "Salon Discord"] fil: Option<ChannelId>) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
         let ecrit = bot.database.get_mut(&object_id).unwrap();
-        ecrit.discord_chan = fil;
-        ecrit.modified = true;
+        let ancien = std::mem::replace(&mut ecrit.discord_chan, fil);
+        ecrit.historiser(&Acteur::from(ctx.author()), Action::LienDiscord,
+                         ancien.map(|chan| format!("<#{chan}>")), fil.map(|chan| format!("<#{chan}>")));
         if fil.is_some() {
             ctx.say(format!("Lien Discord ajouté à l'écrit {}", ecrit.get_name())).await?;
         } else {
@@ -114,22 +191,34 @@ pub async fn lien_discord(ctx: Context<'_, DataType, ErrType>,
 /// Valide un écrit. Si c’est une idée, change son type en rapport.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::MANAGE), check = CommandData::check)]
 pub async fn valider(ctx: Context<'_, DataType, ErrType>,
-                     #[description = "Critère d’identification de l’écrit"] critere: String) -> Result<(), ErrType> {
+                     #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
+        let ecrit = bot.database.get(&object_id).unwrap();
+        let cible = if ecrit.type_ == Type::Idee { Status::EnAttente } else { Status::Valide };
+        if let Err(raison) = ecrit.status.transition(&cible, &ecrit.type_) {
+            ctx.say(raison).await?;
+            return Ok(());
+        }
         bot.archive(vec![object_id]);
         let ecrit = bot.database.get_mut(&object_id).unwrap();
-        if ecrit.type_ == Type::Idee {
-            ecrit.type_ = Type::Rapport;
-            ecrit.status = Status::EnAttente;
+        let acteur = Acteur::from(ctx.author());
+        let idee = ecrit.type_ == Type::Idee;
+        if idee {
+            ecrit.changer_type(Type::Rapport, &acteur);
+            ecrit.changer_statut(Status::EnAttente, &acteur);
             ctx.say(format!("Idée « {} » validée !", ecrit.get_name())).await?;
         } else {
-            ecrit.status = Status::Valide;
+            ecrit.changer_statut(Status::Valide, &acteur);
             ctx.say(format!("Écrit « {} » validé !", ecrit.get_name())).await?;
         }
-        ecrit.modified = true;
         let ecrit = bot.database.get(&object_id).unwrap();
         bot.log(&ctx, format!("{} a validé l'écrit {} (id: {object_id})", tools::user_desc(ctx.author()), ecrit.get_name())).await?;
+        if idee {
+            ecrit.notifier(TypeNotification::Validation, format!("votre idée a été validée par {acteur} : elle est désormais un rapport en attente."));
+        } else {
+            ecrit.notifier_statut(&acteur);
+        }
 
     }
     Ok(())
@@ -138,16 +227,21 @@ pub async fn valider(ctx: Context<'_, DataType, ErrType>,
 /// Refuse un écrit.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::MANAGE), check = CommandData::check)]
 pub async fn refuser(ctx: Context<'_, DataType, ErrType>,
-                     #[description = "Critère d’identification de l’écrit"] critere: String) -> Result<(), ErrType> {
+                     #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
+        let ecrit = bot.database.get(&object_id).unwrap();
+        if let Err(raison) = ecrit.status.transition(&Status::Refuse, &ecrit.type_) {
+            ctx.say(raison).await?;
+            return Ok(());
+        }
         bot.archive(vec![object_id]);
         let ecrit = bot.database.get_mut(&object_id).unwrap();
-        ecrit.status = Status::Refuse;
-        ecrit.modified = true;
+        ecrit.changer_statut(Status::Refuse, &Acteur::from(ctx.author()));
         ctx.say(format!("Écrit « {} » refusé !", ecrit.get_name())).await?;
         let ecrit = bot.database.get(&object_id).unwrap();
         bot.log(&ctx, format!("{} a refusé l'écrit {} (id: {object_id})", tools::user_desc(ctx.author()), ecrit.get_name())).await?;
+        ecrit.notifier_statut(&Acteur::from(ctx.author()));
     }
     Ok(())
 }
@@ -155,34 +249,42 @@ pub async fn refuser(ctx: Context<'_, DataType, ErrType>,
 /// Marque d’intérêt un écrit.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn marquer(ctx: Context<'_, DataType, ErrType>,
-                     #[description = "Critère d’identification de l’écrit"] critere: String,
+                     #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
                      #[description = "Type de l’intérêt"]
-                     #[choices("⊙ Exclusif", "⊟ Immédiat", "⋄ Ouvert", "∙ Intérêt simple", "⋇ Collab recherchée")]
-                     type_: &str,
+                     type_: InteretType,
                      #[description = "Nom de la personne qui marque l’écrit si ce n’est pas la personne exécutant la commande"] procuration: Option<String>) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
-        if bot.database.get(&object_id).unwrap().status != Status::OuvertPlus || bot.database.get(&object_id).unwrap().status != Status::Ouvert {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
+        if bot.database.get(&object_id).unwrap().peut_etre_marque() {
             bot.archive(vec![object_id]);
             let ecrit = bot.database.get_mut(&object_id).unwrap();
             let author_member = ctx.author_member().await.ok_or(ErrType::Generic)?;
             let member_name = procuration.as_ref().unwrap_or(
                 author_member.nick.as_ref().unwrap_or(&author_member.user.name)
             );
-            ctx.say(format!("Écrit « {} » marqué d’intérêt pour {member_name}", ecrit.get_name())).await?;
-
-            ecrit.marquer(Interet {
+            let evenements = match ecrit.marquer(Interet {
                 name: member_name.clone(),
                 date: Timestamp::now(),
-                type_: Interet::get_type(type_).to_string(),
+                type_,
                 member: if procuration.is_none() {author_member.user.id.get()} else {0},
-            });
+                rappel: None,
+                prolongee: None,
+            }, &Acteur::from(ctx.author())) {
+                Ok(evenements) => evenements,
+                Err(refus) => {
+                    ctx.say(refus).await?;
+                    return Ok(());
+                }
+            };
+            ctx.say(format!("Écrit « {} » marqué d’intérêt pour {member_name}", ecrit.get_name())).await?;
             let ecrit = bot.database.get(&object_id).unwrap();
             bot.log(&ctx, format!("{} a marqué un intérêt sur l'écrit {} (id: {object_id}) pour {}",
                                   tools::user_desc(ctx.author()),
                                   ecrit.get_name(),
                                   procuration.unwrap_or(ctx.author().display_name().to_string())
             )).await?;
+            statistiques::enregistrer(evenements);
+            ecrit.notifier_marque();
         } else {
             ctx.say(format!("L’écrit « {} » n’est pas ouvert à la critique.", bot.database.get(&object_id).unwrap().get_name())).await?;
         }
@@ -194,21 +296,21 @@ pub async fn marquer(ctx: Context<'_, DataType, ErrType>,
 /// Libère la marque d’intérêt d’un écrit.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn liberer(ctx: Context<'_, DataType, ErrType>,
-                     #[description = "Critère d’identification de l’écrit"] critere: String,
+                     #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
                      #[description = "Nom de la personne qui a marqué l’écrit si ce n’est pas la personne exécutant la commande"] procuration: Option<String>) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
         if bot.database.get(&object_id).unwrap().status != Status::OuvertPlus || bot.database.get(&object_id).unwrap().status != Status::Ouvert {
             bot.archive(vec![object_id]);
             let ecrit = bot.database.get_mut(&object_id).unwrap();
             let author_member = ctx.author_member().await.ok_or(ErrType::Generic)?;
-            let a_pu_etre_libere =
+            let liberation =
                 if procuration.is_none() {
-                    ecrit.liberer_id(author_member.user.id.get())
+                    ecrit.liberer_id(author_member.user.id.get(), &Acteur::from(ctx.author()))
                 } else {
-                    ecrit.liberer_name(procuration.as_ref().unwrap())
+                    ecrit.liberer_name(procuration.as_ref().unwrap(), &Acteur::from(ctx.author()))
                 };
-            if a_pu_etre_libere {
+            if let Some(evenement) = liberation {
                 ctx.say(format!("Écrit « {} » libéré de la marque de {}", ecrit.get_name(),
                                 procuration.as_ref().unwrap_or(author_member.nick.as_ref().unwrap_or(&author_member.user.name)))).await?;
                 let ecrit = bot.database.get(&object_id).unwrap();
@@ -217,6 +319,7 @@ pub async fn liberer(ctx: Context<'_, DataType, ErrType>,
                                       ecrit.get_name(),
                                       procuration.unwrap_or(ctx.author().display_name().to_string())
                 )).await?;
+                statistiques::enregistrer(vec![evenement]);
             } else {
                 ctx.say(format!("Aucune marque d’intérêt de {} pour l’écrit « {} ».",
                                 procuration.as_ref().unwrap_or(author_member.nick.as_ref().unwrap_or(&author_member.user.name)),
@@ -233,17 +336,24 @@ pub async fn liberer(ctx: Context<'_, DataType, ErrType>,
 /// Indique qu’un écrit a été critiqué et qu’il est désormais en attente.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn critique(ctx: Context<'_, DataType, ErrType>,
-                     #[description = "Critère d’identification de l’écrit"] critere: String) -> Result<(), ErrType> {
+                     #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
         if bot.database.get(&object_id).unwrap().status == Status::Ouvert || bot.database.get(&object_id).unwrap().status == Status::OuvertPlus {
+            let author_member = ctx.author_member().await.ok_or(ErrType::Generic)?;
+            if let Err(refus) = bot.database.get(&object_id).unwrap()
+                .verifier_reservation(author_member.user.id.get(), author_member.nick.as_ref().unwrap_or(&author_member.user.name)) {
+                ctx.say(refus).await?;
+                return Ok(());
+            }
             bot.archive(vec![object_id]);
             let ecrit = bot.database.get_mut(&object_id).unwrap();
-            ecrit.status = Status::EnAttente;
-            ecrit.modified = true;
+            let evenements = ecrit.critique(&Acteur::from(ctx.author()));
             ctx.say(format!("Écrit « {} » critiqué !", ecrit.get_name())).await?;
             let ecrit = bot.database.get(&object_id).unwrap();
             bot.log(&ctx, format!("{} a marqué l'écrit {} (id: {object_id}) comme critiqué.", tools::user_desc(ctx.author()), ecrit.get_name())).await?;
+            statistiques::enregistrer(evenements);
+            ecrit.notifier_critique(&Acteur::from(ctx.author()));
         } else {
             ctx.say(format!("L’écrit « {} » n’est pas ouvert à la critique.", bot.database.get(&object_id).unwrap().get_name())).await?;
         }
@@ -265,10 +375,10 @@ pub async fn archiver_avant(ctx: Context<'_, DataType, ErrType>,
             .map(|(&id, _)| id).collect();
         bot.archive(to_mark.clone());
         let count = to_mark.len();
+        let acteur = Acteur::from(ctx.author());
         to_mark.into_iter().for_each(
             | id | {
-                let ecrit = bot.database.get_mut(&id).unwrap();
-                (ecrit.status, ecrit.modified) = (Status::SansNouvelles, true)
+                bot.database.get_mut(&id).unwrap().changer_statut(Status::SansNouvelles, &acteur);
             }
         );
         ctx.say(format!("{count} écrit(s) ont été marqué(s) sans nouvelles depuis le {}.", date.format("%d %B %Y"))).await?;
@@ -280,127 +390,262 @@ pub async fn archiver_avant(ctx: Context<'_, DataType, ErrType>,
     Ok(())
 }
 
+/// Liste les écrits que l’archivage automatique passera « Sans nouvelles », sans les modifier.
+#[poise::command(slash_command, category = "Base de données", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn apercu_archivage(ctx: Context<'_, DataType, ErrType>,
+                              #[description = "Inclut les écrits archivés d’ici ce nombre de jours (0 par défaut)"] jours: Option<u32>) -> Result<(), ErrType> {
+    if config::get().archivage.is_none() {
+        ctx.say("L’archivage automatique n’est pas configuré.").await?;
+        return Ok(());
+    }
+    let bot = &ctx.data().lock().await;
+    let limite = chrono::Utc::now() + chrono::TimeDelta::days(jours.unwrap_or(0) as i64);
+    let ids = bot.database.iter()
+        .filter(|(_, ecrit)| ecrit.echeance_archivage().is_some_and(|echeance| echeance <= limite))
+        .map(|(&id, _)| id)
+        .collect();
+    Ecrit::naviguer(ctx, bot, "Aperçu de l’archivage automatique", ids, Tri::Date).await
+}
+
+/// Réintègre les écrits d’une sauvegarde d’un flux RSS manqués pendant une panne du bot.
+#[poise::command(slash_command, owners_only, category = "Base de données", custom_data = CommandData::perms(Permission::MANAGE), check = CommandData::check)]
+pub async fn reingerer(ctx: Context<'_, DataType, ErrType>,
+                       #[description = "Fichier XML du flux sauvegardé"] fichier: Attachment,
+                       #[description = "Nom du flux configuré dont provient la sauvegarde"] flux: String) -> Result<(), ErrType> {
+    ctx.defer().await?;
+    let config = config::get();
+    let flux = match config.flux.iter().find(|flux_config| flux_config.nom == flux) {
+        Some(flux) => flux,
+        None => {
+            ctx.say(format!("Flux « {flux} » inconnu. Flux configurés : {}.",
+                            config.flux.iter().map(|flux| flux.nom.as_str()).collect::<Vec<&str>>().join(", "))).await?;
+            return Ok(())
+        }
+    };
+    let rss = Channel::read_from(&fichier.download().await?[..])?;
+    let analyseur = AnalyseurTitre::new(config.balises.clone())?;
+    let bot = &mut ctx.data().lock().await;
+    /* Toutes les entrées de la sauvegarde sont lues : seules celles absentes de la base sont ajoutées. */
+    let (_, ajouts) = Ecrit::ingerer(bot, flux, rss, DateTime::<Utc>::MIN_UTC, &analyseur);
+    if ajouts > 0 {
+        bot.update_affichans = true;
+    }
+    ctx.say(format!("{ajouts} écrit(s) ajouté(s) depuis la sauvegarde du flux {}.", flux.nom)).await?;
+    bot.log(&ctx, format!("{} a réintégré {ajouts} écrit(s) depuis une sauvegarde du flux {}.", tools::user_desc(ctx.author()), flux.nom)).await?;
+    Ok(())
+}
+
 /// Change l’auteur d’un écrit.
 #[poise::command(slash_command, category = "Édition", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn auteur(ctx: Context<'_, DataType, ErrType>,
-                            #[description = "Critère d’identification de l’écrit"] critere: String,
+                            #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
                             #[description = "Nouvel auteur"] auteur: String ) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
         bot.archive(vec![object_id]);
         let ecrit = bot.database.get(&object_id).unwrap();
         ctx.say(format!("L’auteur de l’écrit « {} » changé pour « {auteur} »", ecrit.get_name())).await?;
         bot.log(&ctx, format!("{} a changé l'auteur de {} (id: {object_id}) de {} à {auteur}", tools::user_desc(ctx.author()), ecrit.get_name(), ecrit.auteur)).await?;
         let ecrit = bot.database.get_mut(&object_id).unwrap();
-        ecrit.auteur = auteur;
-        ecrit.modified = true;
+        let ancien = std::mem::replace(&mut ecrit.auteur, auteur.clone());
+        ecrit.historiser(&Acteur::from(ctx.author()), Action::Auteur, Some(ancien), Some(auteur));
+        ecrit.resoudre_auteur();
     }
     Ok(())
 }
 
-/// Liste les écrits avec des critères précis.
-#[poise::command(slash_command, category = "Recherche", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
-pub async fn ulister(ctx: Context<'_, DataType, ErrType>,
-                    #[description = "Inclus dans le nom de l’écrit"] nom: Option<String>,
-                    #[description = "Auteurs, séparés par des virgules"] auteurs: Option<String>,
-                    #[description = "Statuts, séparés par des virgules"] statuts: Option<String>,
-                    #[description = "Types, séparés par des virgules"] types: Option<String>,
-                    #[description = "Tags, séparés par des virgules"] tags: Option<String>,
-                    #[description = "Si Vrai, les écrits doivent posséder tous les tags donnés. Sinon, un seul suffit (défaut)."] tags_et: Option<bool>,
-                    #[description = "Date maximale de dernière modification de l’écrit (jj/mm/aaaa)"] modifie_avant: Option<String>,
-                    #[description = "Date minimale de dernière modification de l’écrit (jj/mm/aaaa)"] modifie_apres: Option<String>) -> Result<(), ErrType> {
-    ctx.defer().await?;
-    let bot = &mut ctx.data().lock().await;
+/// Commence l’inscription de l’utilisateur comme auteur sous un nom wikidot.
+#[poise::command(slash_command, category = "Auteurs", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn inscription_auteur(ctx: Context<'_, DataType, ErrType>,
+                                #[description = "Votre nom d’utilisateur wikidot"] nom: String) -> Result<(), ErrType> {
+    let message = match auteurs::demander_verification(ctx.author().id.get(), &nom) {
+        Ok(code) => format!("Pour prouver que le compte wikidot « {} » vous appartient, ajoutez le code `{code}` dans la section « À propos » de votre profil ({}), \
+                             puis utilisez `/verifier_inscription` dans l’heure. Vous pourrez retirer le code ensuite.",
+                            nom.trim(), auteurs::profil_wikidot(&nom)),
+        Err(raison) => raison
+    };
+    ctx.send(CreateReply::default().content(message).ephemeral(true)).await?;
+    Ok(())
+}
 
-    if nom.is_none() && auteurs.is_none() && statuts.is_none() && types.is_none() && tags.is_none() && modifie_avant.is_none() && modifie_apres.is_none() {
-        ctx.say("Il faut au moins un paramètre non-nul.").await?;
-        return Ok(())
+/// Termine l’inscription de l’utilisateur en vérifiant le code sur son profil wikidot.
+#[poise::command(slash_command, category = "Auteurs", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn verifier_inscription(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
+    ctx.defer_ephemeral().await?;
+    match auteurs::verifier(ctx.author().id.get()).await {
+        Ok(nom) => {
+            ctx.send(CreateReply::default().content(format!("Vous êtes désormais inscrit comme l’auteur « {nom} ».")).ephemeral(true)).await?;
+            let bot = &mut ctx.data().lock().await;
+            Ecrit::resoudre_auteurs(bot);
+            bot.log(&ctx, format!("{} s’est inscrit comme l’auteur wikidot {nom}.", tools::user_desc(ctx.author()))).await?;
+        }
+        Err(raison) => {
+            ctx.send(CreateReply::default().content(raison).ephemeral(true)).await?;
+        }
     }
+    Ok(())
+}
 
-    /* Traitement des paramètres */
-    let nom = basicize(nom.unwrap_or(String::new()).as_str());
-    let mut errs = Vec::new();
-    let statuts = statuts.and_then(|s| {Some(s.split(",").map(Status::from_str)
-        .filter_map(| e | {
-            match e {
-                Ok(s) => Some(s),
-                Err(e) => {errs.push(e); None}
-            }
-        }).collect())}).unwrap_or(Vec::new());
-
-    let types = types.and_then(|s| {Some(s.split(",").map(Type::from_str)
-        .filter_map(| e | {
-            match e {
-                Ok(s) => Some(s),
-                Err(e) => {errs.push(e); None}
-            }
-        }).collect())}).unwrap_or(Vec::new());
-
-    if !errs.is_empty() {
-        return Err(errs.pop().unwrap())
+/// Lie manuellement un nom wikidot à un membre Discord.
+#[poise::command(slash_command, category = "Auteurs", custom_data = CommandData::perms(Permission::MANAGE), check = CommandData::check)]
+pub async fn lier_auteur(ctx: Context<'_, DataType, ErrType>,
+                         #[description = "Membre Discord de l’auteur"] membre: User,
+                         #[description = "Nom wikidot de l’auteur, nouveau nom en cas de renommage"] #[autocomplete = "autocompletion::auteurs"] nom: String) -> Result<(), ErrType> {
+    match auteurs::lier(membre.id.get(), &nom) {
+        Ok(()) => {
+            ctx.say(format!("Le nom wikidot « {} » est désormais lié à {}.", nom.trim(), membre.display_name())).await?;
+            let bot = &mut ctx.data().lock().await;
+            Ecrit::resoudre_auteurs(bot);
+            bot.log(&ctx, format!("{} a lié le nom wikidot {} à {}.",
+                                  tools::user_desc(ctx.author()), nom.trim(), tools::user_desc(&membre))).await?;
+        }
+        Err(raison) => {
+            ctx.say(raison).await?;
+        }
     }
+    Ok(())
+}
 
-    let auteurs = auteurs.and_then(|s| {Some(s.split(",").map(basicize).map(
-        |auteur_critere| {
-            let auteurs_vec = Ecrit::recherche_auteur(&auteur_critere, &bot.database);
-            if auteurs_vec.is_empty() {
-                Err(format!("Aucun auteur correspondant au critère {auteur_critere} trouvé dans la base de données."))
-            } else if auteurs_vec.len() > 1 {
-                Err(format!("Plus d’un auteur de la base de donnée correspond au critère {auteur_critere}."))
-            } else {
-                Ok(auteurs_vec[0])
-            }
+/// Retire un nom wikidot du registre des auteurs.
+#[poise::command(slash_command, category = "Auteurs", custom_data = CommandData::perms(Permission::MANAGE), check = CommandData::check)]
+pub async fn delier_auteur(ctx: Context<'_, DataType, ErrType>,
+                           #[description = "Nom wikidot à délier"] #[autocomplete = "autocompletion::auteurs"] nom: String) -> Result<(), ErrType> {
+    match auteurs::delier(&nom)? {
+        Some(membre) => {
+            ctx.say(format!("Le nom wikidot « {} » n’est plus lié à <@{membre}>.", nom.trim())).await?;
+            let bot = &mut ctx.data().lock().await;
+            Ecrit::resoudre_auteurs(bot);
+            bot.log(&ctx, format!("{} a délié le nom wikidot {} du membre {membre}.",
+                                  tools::user_desc(ctx.author()), nom.trim())).await?;
+        }
+        None => {
+            ctx.say(format!("Le nom wikidot « {} » n’est lié à aucun membre.", nom.trim())).await?;
         }
-    ).collect())}).unwrap_or(Vec::new());
-
-    let auteurs_errors: Vec<&String> = auteurs.iter().filter_map(|res| match res {
-        Err(e) => Some(e),
-        _ => None
-    }).collect();
-
-    if !auteurs_errors.is_empty() {
-        ctx.say(auteurs_errors.into_iter().fold(String::new(), |s, err|
-            s + err.as_str() + "\n"
-        )).await?;
-        return Ok(())
     }
+    Ok(())
+}
 
-    let auteurs: Vec<&String> = auteurs.into_iter().map(|res| res.unwrap()).collect();
+/// Règle les notifications envoyées à l’utilisateur sur ses écrits.
+#[poise::command(slash_command, category = "Auteurs", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn notifications(ctx: Context<'_, DataType, ErrType>,
+                           #[description = "Où recevoir les notifications"] destination: Destination,
+                           #[description = "Quand un écrit est critiqué (oui par défaut)"] critique: Option<bool>,
+                           #[description = "Quand un écrit reçoit une marque d’intérêt (oui par défaut)"] marque: Option<bool>,
+                           #[description = "Quand un écrit est validé (oui par défaut)"] validation: Option<bool>,
+                           #[description = "Quand un écrit est refusé (oui par défaut)"] refus: Option<bool>,
+                           #[description = "Quand un écrit passe sans nouvelles faute de mise à jour (oui par défaut)"] archivage: Option<bool>) -> Result<(), ErrType> {
+    let membre = ctx.author().id.get();
+    let Some(auteur) = auteurs::auteur(membre) else {
+        ctx.send(CreateReply::default().content("Vous n’êtes pas inscrit comme auteur : utilisez d’abord `/inscription_auteur`.").ephemeral(true)).await?;
+        return Ok(());
+    };
+    let precedentes = auteur.notifications;
+    /* Les événements non précisés gardent leur réglage précédent. */
+    let choix = [(TypeNotification::Critique, critique), (TypeNotification::Marque, marque),
+        (TypeNotification::Validation, validation), (TypeNotification::Refus, refus), (TypeNotification::Archivage, archivage)];
+    let evenements: Vec<TypeNotification> = choix.into_iter()
+        .filter(|(type_, choix)| choix.unwrap_or(precedentes.evenements.contains(type_)))
+        .map(|(type_, _)| type_)
+        .collect();
+    let message = if destination == Destination::Aucune {
+        "Notifications désactivées.".to_string()
+    } else if evenements.is_empty() {
+        "Aucun événement choisi : vous ne recevrez aucune notification.".to_string()
+    } else {
+        format!("Notifications activées ({}) pour : {}.", destination.name().to_lowercase(),
+                evenements.iter().map(|type_| type_.cle()).collect::<Vec<_>>().join(", "))
+    };
+    auteurs::definir_notifications(membre, Preferences { destination, evenements })?;
+    ctx.send(CreateReply::default().content(message).ephemeral(true)).await?;
+    Ok(())
+}
 
-    let tags: Vec<String> = tags.and_then(|s| {Some(s.split(",").map(basicize).collect())}).unwrap_or(Vec::new());
-    let modifie_avant = modifie_avant.and_then(parse_date);
-    let modifie_apres = modifie_apres.and_then(parse_date);
+/// Affiche les écrits marqués par l’utilisateur, avec des boutons pour libérer chaque marque.
+#[poise::command(slash_command, category = "Recherche", custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
+pub async fn mes_marques(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
+    let bot = &ctx.data().lock().await;
+    let (embed, composants) = Ecrit::tableau_marques(bot, ctx.author().id.get(), 0);
+    ctx.send(CreateReply::default().embed(embed).components(composants).ephemeral(true)).await?;
+    Ok(())
+}
 
-    let res = tools::sort_by_date(Ecrit::ulister(bot, nom, statuts, types, auteurs, tags, tags_et.unwrap_or(true), modifie_avant, modifie_apres)
-        .into_iter().map(|id| {(id, bot.database.get(id).unwrap())}).collect());
+/// Affiche les écrits de l’utilisateur, ou d’un autre auteur inscrit, avec leur statut.
+#[poise::command(slash_command, category = "Auteurs", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn mes_ecrits(ctx: Context<'_, DataType, ErrType>,
+                        #[description = "Membre dont afficher les écrits, vous-même par défaut"] membre: Option<User>) -> Result<(), ErrType> {
+    let membre = membre.as_ref().unwrap_or(ctx.author());
+    if auteurs::auteur(membre.id.get()).is_none() {
+        ctx.say(if membre.id == ctx.author().id {
+            "Vous n’êtes pas inscrit comme auteur : utilisez d’abord `/inscription_auteur`.".to_string()
+        } else {
+            format!("{} n’est pas inscrit comme auteur.", membre.display_name())
+        }).await?;
+        return Ok(());
+    }
+    let bot = &ctx.data().lock().await;
+    let ids = Ecrit::ecrits_du_membre(bot, membre.id.get()).into_iter().copied().collect();
+    Ecrit::naviguer(ctx, bot, &format!("Écrits de {}", membre.display_name()), ids, Tri::Date).await
+}
 
-    if res.is_empty() {
-        ctx.send(CreateReply::default().embed(CreateEmbed::new()
-            .title("Aucun résultat.")
-            .color(16001600)
-            .author(CreateEmbedAuthor::new(String::from("Recherche personnalisée")))
-            .timestamp(Timestamp::now()))).await?;
-    } else {
-        let embeds = tools::get_multimessages(
-            tools::create_paged_list(res, |(_, ecrit)| ecrit.get_list_entry(), 1000),
-            CreateEmbed::new()
-                .author(CreateEmbedAuthor::new(String::from("Recherche personnalisée")))
-                .title("Résultats de la recherche")
-                .color(73887)
-                .timestamp(Timestamp::now())
-        );
-        bot.send_embed(&ctx, embeds).await?;
+/// Affiche l’historique des modifications d’un écrit.
+#[poise::command(slash_command, category = "Recherche", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn historique(ctx: Context<'_, DataType, ErrType>,
+                        #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String) -> Result<(), ErrType> {
+    let bot = &mut ctx.data().lock().await;
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
+        let ecrit = bot.database.get(&object_id).unwrap();
+        let titre = format!("Historique de « {} »", ecrit.get_name());
+        if ecrit.historique.is_empty() {
+            ctx.send(CreateReply::default().embed(CreateEmbed::new()
+                .title("Aucune modification enregistrée.")
+                .color(16001600)
+                .author(CreateEmbedAuthor::new(titre))
+                .timestamp(Timestamp::now()))).await?;
+        } else {
+            let embeds = tools::get_multimessages(
+                tools::create_paged_list(ecrit.historique.iter().rev().cloned().collect(), |entree| entree.get_list_entry(), 1000),
+                CreateEmbed::new()
+                    .author(CreateEmbedAuthor::new(titre))
+                    .title("Modifications, de la plus récente à la plus ancienne")
+                    .color(73887)
+                    .timestamp(Timestamp::now())
+            );
+            bot.send_embed(&ctx, embeds).await?;
+        }
     }
     Ok(())
 }
 
+/// Liste les écrits correspondant à une requête.
+#[poise::command(slash_command, category = "Recherche", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn ulister(ctx: Context<'_, DataType, ErrType>,
+                    #[description = "Ex. : statut:ouvert,ouvert* type:rapport -tag:humour auteur:\"Dr Foo\" tri:nom"] #[autocomplete = "autocompletion::requete"] requete: String) -> Result<(), ErrType> {
+    ctx.defer().await?;
+    let bot = &mut ctx.data().lock().await;
+
+    let requete = match Requete::analyser(requete.as_str()) {
+        Ok(requete) => requete,
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(())
+        }
+    };
+    if requete.est_vide() {
+        ctx.say("Il faut au moins un critère dans la requête.").await?;
+        return Ok(())
+    }
+
+    let ids = requete.executer(bot).into_iter().map(|(&id, _)| id).collect();
+    Ecrit::naviguer(ctx, bot, "Recherche personnalisée", ids, requete.tri).await
+}
+
 /// Ajoute un tag à l’écrit sélectionné.
 #[poise::command(slash_command, custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn atag(ctx: Context<'_, DataType, ErrType>,
-                    #[description = "Critère d’identification de l’écrit"] critere: String,
-                    #[description = "Tag à ajouter"] tag: String ) -> Result<(), ErrType> {
+                    #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
+                    #[description = "Tag à ajouter"] #[autocomplete = "autocompletion::tags_existants"] tag: String ) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
         let ecrit = bot.database.get(&object_id).unwrap();
         if ecrit.tags.contains(&tag) {
             ctx.say(format!("Le tag « {tag} » est déjà appliqué à l’écrit « {} ».", ecrit.nom))
@@ -408,7 +653,7 @@ pub async fn atag(ctx: Context<'_, DataType, ErrType>,
             bot.archive(vec![object_id]);
             let ecrit = bot.database.get_mut(&object_id).unwrap();
             ecrit.tags.push(tag.clone());
-            ecrit.modified = true;
+            ecrit.historiser(&Acteur::from(ctx.author()), Action::AjoutTag, None, Some(tag.clone()));
             let ecrit = bot.database.get(&object_id).unwrap();
             bot.log(&ctx, format!("{} a ajouté le tag {tag} à l'écrit {} (id: {object_id}).", tools::user_desc(ctx.author()), ecrit.get_name())).await?;
             ctx.say(format!("Le tag « {tag} » a été ajouté à l’écrit « {} » !", ecrit.nom))
@@ -420,10 +665,10 @@ pub async fn atag(ctx: Context<'_, DataType, ErrType>,
 /// Retire des tags à l’écrit sélectionné.
 #[poise::command(slash_command, custom_data = CommandData::perms(Permission::WRITE), check = CommandData::check)]
 pub async fn rtag(ctx: Context<'_, DataType, ErrType>,
-                  #[description = "Critère d’identification de l’écrit"] critere: String,
-                  #[description = "Critère d’identification des tags"] critere_tag: String ) -> Result<(), ErrType> {
+                  #[description = "Critère d’identification de l’écrit"] #[autocomplete = "autocompletion::ecrits"] critere: String,
+                  #[description = "Critère d’identification des tags"] #[autocomplete = "autocompletion::tags_existants"] critere_tag: String ) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    if let Some(object_id) = get_object(&ctx, bot, &critere).await? {
+    if let Some(object_id) = trouver_ecrit(&ctx, bot, &critere).await? {
         let ecrit = bot.database.get(&object_id).unwrap();
         let critere_tag = basicize(critere_tag.as_str());
         let critere_tag = critere_tag.split(" ");
@@ -435,8 +680,10 @@ pub async fn rtag(ctx: Context<'_, DataType, ErrType>,
         if tags_to_keep.len() < ecrit.tags.len() {
             bot.archive(vec![object_id]);
             let ecrit = bot.database.get_mut(&object_id).unwrap();
+            let acteur = Acteur::from(ctx.author());
+            let retires: Vec<String> = ecrit.tags.iter().filter(|tag| !tags_to_keep.contains(tag)).cloned().collect();
             ecrit.tags = tags_to_keep;
-            ecrit.modified = true;
+            retires.into_iter().for_each(|tag| ecrit.historiser(&acteur, Action::RetraitTag, Some(tag), None));
             let ecrit = bot.database.get(&object_id).unwrap();
             bot.log(&ctx, format!("{} a retiré les tags correspondant au critère {critere} à l'écrit {} (id: {object_id}).", tools::user_desc(ctx.author()), ecrit.get_name())).await?;
             ctx.say(format!("Les tags correspondant au critère ont été retirés de l’écrit « {} » !", ecrit.get_name()))
@@ -492,7 +739,7 @@ pub async fn aleatoire(ctx: Context<'_, DataType, ErrType>,
     let bot = &mut ctx.data().lock().await;
     let candidats = Ecrit::ulister(bot, "".to_string(), vec![Status::Ouvert],
                                    type_.and_then(|type_| {Some(vec![type_])}).unwrap_or(Vec::new()),
-                                    Vec::new(), Vec::new(), false, None, None);
+                                    Vec::new(), Vec::new(), ModeTags::Un, None, None, None);
     #[allow(unused_assignments)] /* Necessary to ensure rand falls out of scope before calling the await */
     let mut chosen = None;
     {
@@ -514,19 +761,7 @@ pub async fn ancien(ctx: Context<'_, DataType, ErrType>,
                     #[description = "Type demandé, tous types si non spécifié"]
                     #[rename = "type"] type_: Option<Type>) -> Result<(), ErrType> {
     let bot = &mut ctx.data().lock().await;
-    let candidats = Ecrit::ulister(bot, "".to_string(), vec![Status::Ouvert],
-                                   type_.and_then(|type_| {Some(vec![type_])}).unwrap_or(Vec::new()),
-                                   Vec::new(), Vec::new(), false, None, None);
-    if !candidats.is_empty() {
-        let first = *candidats.first().unwrap();
-        let oldest = candidats.into_iter().fold(first,
-           | oldest, candidat | {
-            if bot.database.get(candidat).unwrap().last_update < bot.database.get(oldest).unwrap().last_update {
-                candidat
-            } else {
-                oldest
-            }
-       });
+    if let Some(&oldest) = Ecrit::plus_anciens(bot, type_).first() {
         ctx.send(CreateReply::default().embed(bot.database.get(oldest).unwrap().get_embed())
             .components(vec![bot.database.get(oldest).unwrap().get_buttons()])).await?;
     } else {
@@ -535,45 +770,129 @@ pub async fn ancien(ctx: Context<'_, DataType, ErrType>,
     Ok(())
 }
 
+/// Affiche les statistiques de critique d’un membre, ou le classement des membres.
+#[poise::command(slash_command, category = "Statistiques", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn stats(ctx: Context<'_, DataType, ErrType>,
+                   #[description = "Membre dont afficher le profil, classement général si absent"] membre: Option<User>,
+                   #[description = "Période prise en compte, depuis toujours par défaut"] periode: Option<Periode>) -> Result<(), ErrType> {
+    let periode = periode.unwrap_or(Periode::Tout);
+    let statistiques = statistiques::calculer(periode);
+    let delai = |delai: Option<chrono::TimeDelta>| delai.map_or("—".to_string(), statistiques::afficher_duree);
+
+    let embed = if let Some(membre) = membre {
+        let profil = statistiques.get(&membre.id.get()).cloned().unwrap_or_default();
+        CreateEmbed::new()
+            .title(format!("Statistiques de {}", membre.display_name()))
+            .fields(vec![
+                ("Critiques", profil.critiques.to_string(), true),
+                ("Marques posées", profil.marques.to_string(), true),
+                ("Marques libérées sans critique", profil.liberations.to_string(), true),
+                ("Délai moyen entre marque et critique", delai(profil.delai_moyen()), true),
+            ])
+    } else {
+        let mut classement: Vec<_> = statistiques.into_values().filter(|profil| profil.critiques > 0).collect();
+        classement.sort_by(|a, b| b.critiques.cmp(&a.critiques).then(b.marques.cmp(&a.marques)));
+        CreateEmbed::new()
+            .title("Classement des critiques")
+            .description(if classement.is_empty() {
+                "Aucune critique sur la période.".to_string()
+            } else {
+                classement.iter().take(10).enumerate().map(|(rang, profil)| format!(
+                    "**{}.** {} — {} critique(s), {} marque(s), délai moyen : {}\n",
+                    rang + 1, profil.nom, profil.critiques, profil.marques, delai(profil.delai_moyen())
+                )).collect::<String>()
+            })
+    };
+    ctx.send(CreateReply::default().embed(embed
+        .author(CreateEmbedAuthor::new("Statistiques"))
+        .footer(CreateEmbedFooter::new(periode.name()))
+        .color(73887)
+        .timestamp(Timestamp::now()))).await?;
+    Ok(())
+}
+
+/// Affiche le bilan de l’activité sur une période.
+#[poise::command(slash_command, category = "Statistiques", custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
+pub async fn bilan(ctx: Context<'_, DataType, ErrType>,
+                   #[description = "Premier jour de la période (jj/mm/aaaa)"] debut: String,
+                   #[description = "Dernier jour de la période (jj/mm/aaaa), aujourd’hui par défaut"] fin: Option<String>) -> Result<(), ErrType> {
+    let Some(debut) = parse_date(debut) else {
+        ctx.say("Date de début invalide : elle doit être au format jj/mm/aaaa.").await?;
+        return Ok(());
+    };
+    let fin = match fin {
+        /* Le dernier jour est compris dans la période. */
+        Some(fin) => match parse_date(fin) {
+            Some(fin) => Timestamp::from(*fin + chrono::TimeDelta::days(1)),
+            None => {
+                ctx.say("Date de fin invalide : elle doit être au format jj/mm/aaaa.").await?;
+                return Ok(());
+            }
+        },
+        None => Timestamp::now()
+    };
+    if fin <= debut {
+        ctx.say("La période doit commencer avant de finir.").await?;
+        return Ok(());
+    }
+    let bot = &ctx.data().lock().await;
+    ctx.send(CreateReply::default().embed(Ecrit::bilan(bot, debut, fin))).await?;
+    Ok(())
+}
+
 /// Affiche la page d’aide du bot.
 #[poise::command(slash_command, prefix_command, custom_data = CommandData::perms(Permission::READ), check = CommandData::check)]
 pub async fn aide(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
     ctx.send(CreateReply::default().embed(CreateEmbed::new()
         .title("Aide de Critibot")
-        .description("Les paramètres entre crochets sont optionnels, entre accolades obligatoires. La description des options est disponible en description des commandes slash.")
+        .description("Les paramètres entre crochets sont optionnels, entre accolades obligatoires. La description des options est disponible en description des commandes slash. Les critères d'identification des écrits, les tags et les requêtes de `/ulister` sont proposés en autocomplétion.")
         .fields(vec![
             ("Commandes de base",
              "`/aide` : Cette commande d'aide.\n\
             `/annuler` : Annule la dernière modification effectuée.", false),
             ("Commandes de gestion et d'affichage de la liste",
             "`/ajouter {Nom} {Auteur} {Type} {Statut} {URL}` : Ajoute manuellement un écrit à la liste.\n\
-            `/supprimer {Critère}` : Supprime un écrit. Le Critère doit être assez fin pour aboutir à un unique écrit. __**ATTENTION**__ : Il n'y a pas de confirmation, faites attention à ne pas vous tromper dans le Critère.\n", false),
+            `/supprimer {Critère}` : Supprime un écrit. Le Critère doit être assez fin pour aboutir à un unique écrit. __**ATTENTION**__ : Il n'y a pas de confirmation, faites attention à ne pas vous tromper dans le Critère.\n\
+            `/statut {Critère} {Statut} [Forcer]` : Change le statut d'un écrit si la transition est autorisée. L'équipe critique peut forcer une transition interdite.\n", false),
             ("Commandes de recherche",
             "`/rechercher {Critère}` : Affiche tous les écrits contenant {Critère}.\n\
-            `/lister {Statut} [Type]` : Affiche la liste des écrits avec le statut et du type demandés.\n\
-            `/lister_tags` : Affiche tous les tags existants dans la base de données et le nombre d'écrits y étant associés.", false),
+            `/lister [Statut] [Type] [Tags] [Mode des tags]` : Affiche la liste des écrits avec le statut, du type et avec les tags demandés. Les tags sont séparés par des virgules ; les écrits doivent posséder l'un d'eux, tous ou aucun selon le mode choisi. Les listes de résultats se parcourent avec les boutons et se trient avec le menu sous le message.\n\
+            `/historique {Critère}` : Affiche l'historique des modifications d'un écrit.\n\
+            `/lister_tags` : Affiche tous les tags existants dans la base de données et le nombre d'écrits y étant associés.\n\
+            `/stats [Membre] [Période]` : Affiche les critiques, marques et délais de critique d'un membre, ou le classement des critiques si aucun membre n'est donné.\n\
+            `/bilan {Début} [Fin]` : Affiche le bilan de l'activité sur la période : nouveaux écrits, critiques, validations, écrits ouverts les plus anciens et marques bientôt retirées.", false),
             ("Commandes de critique",
             "`/marquer {Critère} [Procuration]` : Ajoute une marque d'intérêt à un écrit. Le Critère doit être assez fin pour aboutir à un unique écrit.\n\
             `/libérer {Critère} [Procuration]` : Supprime une marque d'intétêt sur un écrit. Le Critère doit être assez fin pour aboutir à un unique écrit.\n\
             `/up {Critère}` : Marque un écrit ouvert et le remet au premier plan dans le salon des fils ouverts s'il l'était déjà. Le Critère doit être assez fin pour aboutir à un unique écrit.\n\
+            `/mes_marques` : Affiche les écrits que vous avez marqués, avec le type et l'âge de chaque marque et des boutons pour la libérer ou marquer l'écrit comme critiqué.\n\
             `/valider {Critère}` : Change le type du rapport en Rapport et le marque En Attente si c'était une idée, règle le statut à Validé sinon. Le Critère doit être assez fin pour aboutir à un unique écrit.", false),
             ("Commandes d'entretien de la base de données (À utiliser avec précaution)",
             "`/nettoyer` : Supprime tous les écrits abandonnés / refusés / publiés de la liste.\n\
             `/archiver_avant {Date}` : Met le statut « sans nouvelles » à tous les écrits n'ayant pas été mis à jour avant la date indiquée. La date doit être au format dd/mm/yyyy.\n\
+            `/apercu_archivage [Jours]` : Liste les écrits que l'archivage automatique marquera sans nouvelles, maintenant ou d'ici le nombre de jours donné, sans les modifier.\n\
             `/doublons` : Supprime les éventuels doublons.", false),
+            ("Commandes d’auteur",
+            "`/inscription_auteur {Nom wikidot}` : Donne un code à afficher sur votre profil wikidot pour prouver que le compte vous appartient.\n\
+            `/verifier_inscription` : Vérifie le code sur votre profil wikidot et vous lie au nom d'auteur correspondant.\n\
+            `/lier_auteur {Membre} {Nom wikidot}` : Lie manuellement un nom wikidot à un membre. Un membre peut avoir plusieurs noms, par exemple après un renommage.\n\
+            `/delier_auteur {Nom wikidot}` : Retire un nom wikidot du registre des auteurs.\n\
+            `/mes_ecrits [Membre]` : Affiche vos écrits, ou ceux d'un autre auteur inscrit, avec leur statut.\n\
+            `/notifications {Destination} [Événements]` : Choisit de recevoir ou non, en message privé ou dans le fil de l'écrit, une notification quand un de vos écrits est critiqué, marqué, validé, refusé ou archivé.", false),
             ("Commandes de choix d’écrit",
             "`/aléatoire [Type]` : Choisit un écrit ouvert aléatoire du type donné en paramètre. Si aucun argument n'est donné, chosit un écrit ouvert aléatoire sans distinction de type.\n\
             `/ancien [Type]` : Choisit l'écrit le plus anciennement modifié encore ouvert du type donné en paramètre. Si aucun argument n'est donné, choisit l'écrit encore ouvert le plus ancien sans distinction de type.", false),
             ("Recherche avancée",
-            "La recherche avancée est utilisable avec `/ulister`. En mode texte, chaque paramètre est de la forme `nom=valeur`. Les différents paramètres disponibles sont :\n\
-            `nom: {Critère}` : Réduit la recherche aux écrits dont le nom correspond au critère.\n\
+            "La recherche avancée est utilisable avec `/ulister {Requête}`. La requête est une suite de termes séparés par des espaces, de la forme `clé:valeur,valeur,…`. Les valeurs contenant des espaces s'écrivent entre guillemets. Les clés disponibles sont :\n\
+            `nom: {Critère}` : Le nom de l'écrit doit contenir le critère. Un mot sans clé est également cherché dans le nom.\n\
             `statut: {Statut},{Statut},…` : Les écrits doivent avoir l'un des statuts de la liste.\n\
             `type: {Type},{Type},…` : Les écrits doivent avoir l'un des types de la liste.\n\
             `auteur: {Critère auteur},{Critère auteur},…` : Les écrits doivent être d'un des auteurs de la liste.\n\
-            `tag: {Critère tag},{Critère tag},…` : Les écrits doivent posséder l'un des tags de la liste.\n\
-            `tag_et` : Si \"Vrai\", l’écrit doit posséder tous les tags trouvés plutôt qu’un seul.\n\
+            `tag: {Tag},{Tag},…` : Les écrits doivent posséder l'un des tags de la liste. Avec `tag: {Tag}+{Tag}+…`, ils doivent les posséder tous, et avec `-tag: {Tag},{Tag},…` n'en posséder aucun.\n\
             `avant: {jj/mm/aaaa}` : Les écrits doivent avoir été modifiés pour la dernière fois avant la date indiquée.\n\
-            `après: {jj/mm/aaaa}` : Les écirts doivent avoir été modifiés pour la dernière fois après la date indiquée.", false),
+            `après: {jj/mm/aaaa}` : Les écrits doivent avoir été modifiés pour la dernière fois après la date indiquée.\n\
+            `tri: {date|nom|auteur|marques}` : Ordre initial des résultats : par date de dernière modification (défaut), par nom, par auteur ou par nombre de marques.\n\
+            Tous les termes doivent être vérifiés. Un `-` devant un terme l'inverse (`-tag:humour`) et le mot `ou` sépare des groupes de termes dont un seul doit être vérifié.", false),
             ("Code source", "Disponible sur [Github](https://github.com/Fondation-SCP/critibot).", false)
         ])
         .footer(CreateEmbedFooter::new("Version 4.2.0 (Rust 1.2.0)"))
@@ -587,5 +906,7 @@ pub fn command_list() -> Vec<Command<DataType, ErrType>> {
     vec![ajouter(), lister(), nettoyer(), statut(), type_(), marquer(), liberer(), critique(),
          archiver_avant(), auteur(), ulister(), atag(), rtag(), lister_tags(), alias("ajouter_tag", atag()),
         alias("retirer_tag", rtag()), alias("supprimer_tag", rtag()), aleatoire(), alias("random", aleatoire()),
-        ancien(), aide(), alias("help", aide()), valider(), lien_discord()]
+        ancien(), aide(), alias("help", aide()), valider(), lien_discord(), reingerer(), historique(), stats(), bilan(), apercu_archivage(),
+         inscription_auteur(), verifier_inscription(), lier_auteur(), delier_auteur(), notifications(),
+         mes_marques(), mes_ecrits()]
 }
@@ -0,0 +1,396 @@
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use chrono::{TimeDelta, Weekday};
+use fondabots_lib::tools::basicize;
+use fondabots_lib::yaml_rust2::{Yaml, YamlLoader};
+use fondabots_lib::ErrType;
+use poise::serenity_prelude as serenity;
+use serenity::all::{ChannelId, RoleId, UserId};
+use strum::IntoEnumIterator;
+
+use crate::ecrit::fields::{InteretType, Status, Type};
+use crate::ecrit::titre::RegleBalise;
+
+/// Chemin par défaut du fichier de configuration, à côté de `critibot.yml`.
+const CHEMIN_DEFAUT: &str = "./critibot.config.yml";
+
+/// Chemin par défaut du registre des auteurs.
+const CHEMIN_AUTEURS: &str = "./critibot.auteurs.yml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Salons d’affichage des écrits.
+#[derive(Debug, Clone)]
+pub struct AffichansConfig {
+    /// Écrits ouverts à la critique (Ouvert et Ouvert*).
+    pub ouverts: ChannelId,
+    /// Écrits au statut inconnu ou en infraction.
+    pub inconnus: ChannelId,
+    /// Écrits de type Autre.
+    pub autres: ChannelId,
+}
+
+/// Rôles Discord utilisés pour les permissions des commandes.
+#[derive(Debug, Clone)]
+pub struct RolesConfig {
+    /// Rôle donnant la permission WRITE (Classe-C membre).
+    pub membre: RoleId,
+    /// Rôle donnant la permission MANAGE (Équipe Critique).
+    pub equipe_critique: RoleId,
+    /// Rôle donnant toutes les permissions (Staff).
+    pub staff: RoleId,
+}
+
+/// Flux RSS d’une catégorie du forum des critiques.
+#[derive(Debug, Clone)]
+pub struct Flux {
+    /// Nom du flux, enregistré dans chaque écrit qui en provient.
+    pub nom: String,
+    pub url: String,
+    /// Type donné aux écrits dont les balises du titre ne permettent pas de deviner le type.
+    pub type_: Type,
+    /// Statut donné aux nouveaux écrits.
+    pub statut: Status,
+    /// Salon affichant les écrits ouverts provenant de ce flux.
+    pub affichan: Option<ChannelId>,
+}
+
+/// Délais après lesquels une marque d’intérêt d’un type donné est rappelée à son auteur, puis retirée.
+#[derive(Debug, Clone)]
+pub struct DelaisMarque {
+    pub type_: InteretType,
+    pub rappel: TimeDelta,
+    pub expiration: TimeDelta,
+}
+
+/// Durées attachées aux marques d’intérêt.
+#[derive(Debug, Clone)]
+pub struct MarquesConfig {
+    /// Durée pendant laquelle une marque exclusive réserve l’écrit à son auteur.
+    pub reservation_exclusive: TimeDelta,
+    /// Durée au bout de laquelle une marque immédiate expire.
+    pub expiration_immediate: TimeDelta,
+    /// Intervalle entre deux vérifications des marques anciennes.
+    pub intervalle_verification: TimeDelta,
+    /// Délais de rappel et de retrait par type de marque. Les types absents ne sont jamais retirés.
+    pub delais: Vec<DelaisMarque>,
+}
+
+impl MarquesConfig {
+    pub fn delais(&self, type_: &InteretType) -> Option<&DelaisMarque> {
+        self.delais.iter().find(|delais| delais.type_ == *type_)
+    }
+}
+
+impl DelaisMarque {
+    /// Délais utilisés pour les types de marque que la configuration ne mentionne pas, en jours.
+    fn par_defaut(type_: &InteretType) -> Option<Self> {
+        let (rappel, expiration) = match type_ {
+            InteretType::Exclusif => (5, 7),
+            InteretType::Immediat => return None,
+            InteretType::Ouvert => (10, 14),
+            InteretType::Simple | InteretType::Collab => (21, 30),
+        };
+        Some(Self {
+            type_: type_.clone(),
+            rappel: TimeDelta::days(rappel),
+            expiration: TimeDelta::days(expiration),
+        })
+    }
+
+    fn from_yaml(yaml: &Yaml, type_: InteretType) -> Result<Self, ErrType> {
+        let cle = type_.cle();
+        let delais = Self {
+            rappel: TimeDelta::days(get_positif(yaml, "rappel", 0)
+                .map_err(|e| ErrType::YamlParseError(format!("Configuration des délais de marque {cle} : {e}")))?),
+            expiration: TimeDelta::days(get_positif(yaml, "expiration", 0)
+                .map_err(|e| ErrType::YamlParseError(format!("Configuration des délais de marque {cle} : {e}")))?),
+            type_,
+        };
+        if delais.rappel >= delais.expiration {
+            return Err(ErrType::YamlParseError(format!(
+                "Configuration des délais de marque {cle} : le rappel doit précéder l’expiration.")));
+        }
+        Ok(delais)
+    }
+}
+
+/// Publication hebdomadaire du bilan de l’activité.
+#[derive(Debug, Clone)]
+pub struct BilanConfig {
+    pub salon: ChannelId,
+    pub jour: Weekday,
+    /// Heure locale de publication, de 0 à 23.
+    pub heure: u32,
+}
+
+impl BilanConfig {
+    const JOURS: [(&'static str, Weekday); 7] = [
+        ("lundi", Weekday::Mon),
+        ("mardi", Weekday::Tue),
+        ("mercredi", Weekday::Wed),
+        ("jeudi", Weekday::Thu),
+        ("vendredi", Weekday::Fri),
+        ("samedi", Weekday::Sat),
+        ("dimanche", Weekday::Sun),
+    ];
+
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ErrType> {
+        let jour = match yaml["jour"].as_str() {
+            None => Weekday::Mon,
+            Some(jour) => Self::JOURS.iter().find(|(nom, _)| basicize(jour) == *nom).map(|(_, jour)| *jour)
+                .ok_or(ErrType::YamlParseError(format!("Configuration : bilan.jour « {jour} » n’est pas un jour de la semaine.")))?
+        };
+        let heure = match &yaml["heure"] {
+            Yaml::BadValue => 9,
+            Yaml::Integer(heure) if (0..24).contains(heure) => *heure as u32,
+            _ => return Err(ErrType::YamlParseError("Configuration : bilan.heure doit être une heure entre 0 et 23.".to_string()))
+        };
+        Ok(Self {
+            salon: ChannelId::new(get_id(yaml, "salon")
+                .map_err(|e| ErrType::YamlParseError(format!("Configuration du bilan : {e}")))?),
+            jour,
+            heure,
+        })
+    }
+}
+
+/// Passage automatique au statut Sans nouvelles des écrits ouverts qui ne sont plus mis à jour.
+#[derive(Debug, Clone)]
+pub struct ArchivageConfig {
+    /// Ancienneté de la dernière mise à jour au-delà de laquelle un écrit est archivé, par type.
+    /// Les types absents ne sont jamais archivés automatiquement.
+    pub ages: Vec<(Type, TimeDelta)>,
+    /// Délai entre l’avertissement de l’auteur et l’archivage.
+    pub avertissement: TimeDelta,
+}
+
+impl ArchivageConfig {
+    pub fn age(&self, type_: &Type) -> Option<TimeDelta> {
+        self.ages.iter().find(|(type_age, _)| type_age == type_).map(|(_, age)| *age)
+    }
+
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ErrType> {
+        let ages = match &yaml["ages"] {
+            Yaml::Hash(ages) => ages.iter().filter(|(_, age)| !age.is_null()).map(|(type_, age)| {
+                let type_ = type_.as_str().ok_or(ErrType::YamlParseError(
+                    "Configuration : les clés de archivage.ages doivent être des types d’écrit.".to_string()))?;
+                let type_ = Type::from_str(type_)
+                    .map_err(|e| ErrType::YamlParseError(format!("Configuration de l’archivage : {e}")))?;
+                match age {
+                    Yaml::Integer(jours) if *jours > 0 => Ok((type_, TimeDelta::days(*jours))),
+                    _ => Err(ErrType::YamlParseError(format!("Configuration : archivage.ages.{type_} doit être un nombre positif.")))
+                }
+            }).collect::<Result<Vec<_>, ErrType>>()?,
+            _ => return Err(ErrType::YamlParseError("Configuration : archivage.ages doit associer un nombre de jours à des types.".to_string()))
+        };
+        let archivage = Self {
+            ages,
+            avertissement: TimeDelta::days(get_positif(yaml, "avertissement", 7)?),
+        };
+        if let Some((type_, _)) = archivage.ages.iter().find(|(_, age)| *age <= archivage.avertissement) {
+            return Err(ErrType::YamlParseError(format!(
+                "Configuration : l’âge d’archivage du type {type_} doit dépasser le délai d’avertissement.")));
+        }
+        Ok(archivage)
+    }
+}
+
+/// Configuration du bot propre à un serveur Discord.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub proprietaire: UserId,
+    pub salon_log: ChannelId,
+    pub forum_critiques: ChannelId,
+    pub affichans: AffichansConfig,
+    pub roles: RolesConfig,
+    pub flux: Vec<Flux>,
+    /// Suit les fils des écrits en attente ou sans nouvelles pour les relancer quand l’auteur y répond.
+    pub suivi_fils: bool,
+    /// Remplace le protocole et l’hôte des URL des flux, pour lire les flux depuis un serveur local.
+    pub url_base: Option<String>,
+    /// Règles de détection du type d’un écrit à partir des balises de son titre.
+    pub balises: Vec<RegleBalise>,
+    pub marques: MarquesConfig,
+    /// Base SQLite des écrits, qui remplace la base YAML (fonctionnalité `sqlite`).
+    pub sqlite: Option<String>,
+    /// Registre des auteurs, séparé de la base de données pour survivre au nettoyage des écrits.
+    pub auteurs: String,
+    /// Bilan hebdomadaire, désactivé si absent.
+    pub bilan: Option<BilanConfig>,
+    /// Archivage automatique, désactivé si absent.
+    pub archivage: Option<ArchivageConfig>,
+}
+
+impl Config {
+    /// Charge la configuration depuis le fichier donné par la variable d’environnement
+    /// `CRITIBOT_CONFIG`, ou depuis `critibot.config.yml` par défaut.
+    pub fn load() -> Result<Self, ErrType> {
+        let chemin = env::var("CRITIBOT_CONFIG").unwrap_or(CHEMIN_DEFAUT.to_string());
+        let contenu = fs::read_to_string(&chemin).map_err(|e|
+            ErrType::YamlParseError(format!("Impossible de lire le fichier de configuration {chemin} : {e}"))
+        )?;
+        Self::parse(contenu.as_str())
+    }
+
+    pub fn parse(contenu: &str) -> Result<Self, ErrType> {
+        let docs = YamlLoader::load_from_str(contenu)
+            .map_err(|e| ErrType::YamlParseError(format!("Fichier de configuration invalide : {e}")))?;
+        let yaml = docs.first()
+            .ok_or(ErrType::YamlParseError("Fichier de configuration vide.".to_string()))?;
+
+        let config = Self {
+            proprietaire: UserId::new(get_id(yaml, "proprietaire")?),
+            salon_log: ChannelId::new(get_id(yaml, "salon-log")?),
+            forum_critiques: ChannelId::new(get_id(yaml, "forum-critiques")?),
+            affichans: AffichansConfig {
+                ouverts: ChannelId::new(get_id(yaml, "affichans.ouverts")?),
+                inconnus: ChannelId::new(get_id(yaml, "affichans.inconnus")?),
+                autres: ChannelId::new(get_id(yaml, "affichans.autres")?),
+            },
+            roles: RolesConfig {
+                membre: RoleId::new(get_id(yaml, "roles.membre")?),
+                equipe_critique: RoleId::new(get_id(yaml, "roles.equipe-critique")?),
+                staff: RoleId::new(get_id(yaml, "roles.staff")?),
+            },
+            flux: match &yaml["flux"] {
+                /* Flux historique des brouillons, utilisé si aucun flux n’est configuré. */
+                Yaml::BadValue => vec![Flux {
+                    nom: "brouillons".to_string(),
+                    url: "http://fondationscp.wikidot.com/feed/forum/ct-656675.xml".to_string(),
+                    type_: Type::Rapport,
+                    statut: Status::Ouvert,
+                    affichan: None,
+                }],
+                Yaml::Array(flux) => flux.iter().enumerate()
+                    .map(|(i, flux)| Flux::from_yaml(flux, i))
+                    .collect::<Result<Vec<Flux>, ErrType>>()?,
+                _ => return Err(ErrType::YamlParseError("Configuration : flux doit être une liste.".to_string()))
+            },
+            suivi_fils: yaml["suivi-fils"].as_bool().unwrap_or(true),
+            url_base: yaml["url-base"].as_str().map(|url| url.to_string()),
+            balises: match &yaml["balises"] {
+                Yaml::BadValue => RegleBalise::par_defaut(),
+                Yaml::Array(regles) => regles.iter().enumerate()
+                    .map(|(i, regle)| regle_from_yaml(regle, i))
+                    .collect::<Result<Vec<RegleBalise>, ErrType>>()?,
+                _ => return Err(ErrType::YamlParseError("Configuration : balises doit être une liste.".to_string()))
+            },
+            sqlite: yaml["sqlite"].as_str().map(|chemin| chemin.to_string()),
+            auteurs: yaml["auteurs"].as_str().unwrap_or(CHEMIN_AUTEURS).to_string(),
+            bilan: match &yaml["bilan"] {
+                Yaml::BadValue => None,
+                bilan => Some(BilanConfig::from_yaml(bilan)?)
+            },
+            archivage: match &yaml["archivage"] {
+                Yaml::BadValue => None,
+                archivage => Some(ArchivageConfig::from_yaml(archivage)?)
+            },
+            marques: MarquesConfig {
+                reservation_exclusive: TimeDelta::hours(get_positif(yaml, "marques.reservation-exclusive", 72)?),
+                expiration_immediate: TimeDelta::hours(get_positif(yaml, "marques.expiration-immediate", 2)?),
+                intervalle_verification: TimeDelta::minutes(get_positif(yaml, "marques.verification-minutes", 60)?),
+                delais: InteretType::iter().filter_map(|type_| match &yaml["marques"]["delais"][type_.cle()] {
+                    Yaml::BadValue => DelaisMarque::par_defaut(&type_).map(Ok),
+                    Yaml::Null => None,
+                    delais => Some(DelaisMarque::from_yaml(delais, type_))
+                }).collect::<Result<Vec<DelaisMarque>, ErrType>>()?,
+            },
+        };
+        config.valider()?;
+        Ok(config)
+    }
+
+    fn valider(&self) -> Result<(), ErrType> {
+        let affichans = [self.affichans.ouverts, self.affichans.inconnus, self.affichans.autres];
+        if affichans.iter().enumerate().any(|(i, chan)| affichans[..i].contains(chan)) {
+            return Err(ErrType::YamlParseError(
+                "Configuration : les trois affichans doivent être des salons différents.".to_string()
+            ));
+        }
+        if self.flux.is_empty() {
+            return Err(ErrType::YamlParseError("Configuration : la liste des flux est vide.".to_string()));
+        }
+        if let Some(flux) = self.flux.iter().enumerate()
+            .find(|(i, flux)| self.flux[..*i].iter().any(|autre| autre.nom == flux.nom))
+            .map(|(_, flux)| flux) {
+            return Err(ErrType::YamlParseError(format!("Configuration : le flux {} est défini plusieurs fois.", flux.nom)));
+        }
+        Ok(())
+    }
+}
+
+impl Flux {
+    fn from_yaml(yaml: &Yaml, index: usize) -> Result<Self, ErrType> {
+        let get_str = |cle: &str| yaml[cle].as_str().ok_or(
+            ErrType::YamlParseError(format!("Configuration : champ {cle} manquant dans le flux n°{}.", index + 1))
+        );
+        let nom = get_str("nom")?.to_string();
+        let erreur = |e: ErrType| ErrType::YamlParseError(format!("Configuration du flux {nom} : {e}"));
+        Ok(Self {
+            url: get_str("url")?.to_string(),
+            type_: Type::from_str(get_str("type")?).map_err(erreur)?,
+            statut: match yaml["statut"].as_str() {
+                Some(statut) => Status::from_str(statut).map_err(erreur)?,
+                None => Status::Ouvert
+            },
+            affichan: match &yaml["affichan"] {
+                Yaml::BadValue => None,
+                _ => Some(ChannelId::new(get_id(yaml, "affichan").map_err(erreur)?))
+            },
+            nom,
+        })
+    }
+}
+
+fn regle_from_yaml(yaml: &Yaml, index: usize) -> Result<RegleBalise, ErrType> {
+    let erreur = |champ: &str| ErrType::YamlParseError(
+        format!("Configuration : champ {champ} manquant ou invalide dans la règle de balise n°{}.", index + 1)
+    );
+    let type_ = Type::from_str(yaml["type"].as_str().ok_or(erreur("type"))?)
+        .map_err(|e| ErrType::YamlParseError(format!("Configuration de la règle de balise n°{} : {e}", index + 1)))?;
+    let mots = yaml["mots"].as_vec().ok_or(erreur("mots"))?.iter()
+        .map(|mot| mot.as_str().ok_or(erreur("mots")))
+        .collect::<Result<Vec<&str>, ErrType>>()?;
+    if mots.is_empty() {
+        return Err(erreur("mots"));
+    }
+    Ok(RegleBalise::new(mots.as_slice(), type_))
+}
+
+/// Lit un identifiant Discord à partir d’un chemin de clés séparées par des points.
+pub(crate) fn get_id(yaml: &Yaml, chemin: &str) -> Result<u64, ErrType> {
+    let valeur = chemin.split(".").fold(yaml, |yaml, cle| &yaml[cle]);
+    match valeur {
+        Yaml::BadValue => Err(ErrType::YamlParseError(format!("Configuration : champ {chemin} manquant."))),
+        Yaml::Integer(id) if *id > 0 => Ok(*id as u64),
+        Yaml::String(id) => id.parse().ok().filter(|&id: &u64| id > 0)
+            .ok_or(ErrType::YamlParseError(format!("Configuration : {chemin} n’est pas un identifiant Discord valide."))),
+        _ => Err(ErrType::YamlParseError(format!("Configuration : {chemin} n’est pas un identifiant Discord valide.")))
+    }
+}
+
+/// Lit un nombre strictement positif à partir d’un chemin de clés séparées par des points. Un
+/// défaut nul rend le champ obligatoire.
+fn get_positif(yaml: &Yaml, chemin: &str, defaut: i64) -> Result<i64, ErrType> {
+    match chemin.split(".").fold(yaml, |yaml, cle| &yaml[cle]) {
+        Yaml::BadValue if defaut > 0 => Ok(defaut),
+        Yaml::Integer(nombre) if *nombre > 0 => Ok(*nombre),
+        _ => Err(ErrType::YamlParseError(format!("Configuration : {chemin} doit être un nombre positif.")))
+    }
+}
+
+/// Initialise la configuration globale. Ne peut être appelée qu’une seule fois.
+pub fn init(config: Config) {
+    if CONFIG.set(config).is_err() {
+        panic!("La configuration a déjà été initialisée.");
+    }
+}
+
+/// Configuration globale du bot. Panique si elle n’a pas été initialisée au démarrage.
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("Configuration non initialisée.")
+}
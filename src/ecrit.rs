@@ -1,9 +1,7 @@
-use std::cmp::max;
 use std::collections::HashMap;
-use std::mem::take;
 use std::str::FromStr;
 
-use chrono::DateTime;
+use chrono::Utc;
 use fondabots_lib;
 use fondabots_lib::object::Field;
 use fondabots_lib::tools::basicize;
@@ -11,18 +9,51 @@ use fondabots_lib::yaml_rust2::{yaml, Yaml};
 use fondabots_lib::ErrType;
 use fondabots_lib::{tools, DataType};
 use fondabots_lib::{Bot, Object};
-use poise::serenity_prelude as serenity;
+use poise::{serenity_prelude as serenity, ChoiceParameter};
 use regex::Regex;
-use rss::Channel;
 use serenity::all::{ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, EditMessage, Timestamp};
 use serenity::all::{ChannelId, Context as SerenityContext};
 use serenity::builder::CreateEmbedAuthor;
 
-use fields::Interet;
+use crate::{config, permissions};
+use fields::{Interet, InteretType};
 use fields::Status;
 use fields::Type;
+use historique::{Acteur, Action, EntreeHistorique};
+use statistiques::{Evenement, TypeEvenement};
 
+pub mod archivage;
+pub mod auteurs;
+pub mod bilan;
+pub mod confirmation;
 pub mod fields;
+pub mod flux;
+pub mod historique;
+pub mod marques;
+pub mod migrations;
+pub mod navigateur;
+pub mod notifications;
+pub mod quarantaine;
+pub mod requete;
+pub mod tableaux;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod statistiques;
+pub mod titre;
+
+/// Manière de combiner les tags demandés dans [`Ecrit::ulister`].
+#[derive(ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ModeTags {
+    /// L’écrit possède tous les tags demandés.
+    #[name = "Tous les tags"]
+    Tous,
+    /// L’écrit possède au moins un des tags demandés.
+    #[name = "L’un des tags"]
+    Un,
+    /// L’écrit ne possède aucun des tags demandés.
+    #[name = "Aucun des tags"]
+    Aucun,
+}
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Ecrit {
@@ -36,6 +67,12 @@ pub struct Ecrit {
     pub modified: bool,
     pub tags: Vec<String>,
     pub discord_chan: Option<ChannelId>,
+    /// Nom du flux RSS dont provient l’écrit, `None` s’il a été ajouté à la main.
+    pub flux: Option<String>,
+    pub historique: Vec<EntreeHistorique>,
+    /// Membre Discord lié à l’auteur dans le registre des auteurs, résolu à l’ajout de l’écrit puis à
+    /// chaque changement de l’auteur ou du registre (voir [`Ecrit::resoudre_auteur`]).
+    pub membre_auteur: Option<u64>,
     id: u64,
 }
 
@@ -54,19 +91,82 @@ impl Ecrit {
             id: Self::find_id(&lien).unwrap(),
             lien,
             last_update: Timestamp::now(),
+            membre_auteur: auteurs::membre(&auteur),
             auteur,
             interesses: Vec::new(),
             modified: false,
             tags: Vec::new(),
             discord_chan: None,
+            flux: None,
+            historique: Vec::new(),
         })
     }
 
-    pub fn critique(&mut self) {
+    /// Ajoute une entrée à l’historique de l’écrit.
+    pub fn historiser(&mut self, acteur: &Acteur, action: Action, ancien: Option<String>, nouveau: Option<String>) {
+        self.historique.push(EntreeHistorique {
+            date: Timestamp::now(),
+            acteur: acteur.clone(),
+            action,
+            ancien,
+            nouveau,
+        });
+        self.modified = true;
+    }
+
+    /// Change le statut de l’écrit en l’enregistrant dans l’historique.
+    pub fn changer_statut(&mut self, statut: Status, acteur: &Acteur) {
+        if self.status != statut {
+            let ancien = self.status.to_string();
+            self.status = statut;
+            self.historiser(acteur, Action::Statut, Some(ancien), Some(self.status.to_string()));
+        }
+        self.modified = true;
+    }
+
+    /// Change le type de l’écrit en l’enregistrant dans l’historique.
+    pub fn changer_type(&mut self, type_: Type, acteur: &Acteur) {
+        if self.type_ != type_ {
+            let ancien = self.type_.to_string();
+            self.type_ = type_;
+            self.historiser(acteur, Action::Type, Some(ancien), Some(self.type_.to_string()));
+        }
+        self.modified = true;
+    }
+
+    /// Remet l’écrit au premier plan (voir [`Object::up`]) en l’enregistrant dans l’historique.
+    pub fn up_par(&mut self, acteur: &Acteur) {
+        let ancien = self.status.clone();
+        self.up();
+        if self.status != ancien {
+            self.historiser(acteur, Action::Statut, Some(ancien.to_string()), Some(self.status.to_string()));
+        }
+        self.modified = true;
+    }
+
+    /// Marque l’écrit comme critiqué et retire ses marques. Renvoie les événements de statistiques de
+    /// la critique, à enregistrer une fois l’action effectuée.
+    pub fn critique(&mut self, acteur: &Acteur) -> Vec<Evenement> {
+        let critique = match acteur {
+            Acteur::Membre { id, nom } => Some((*id, nom.as_str())),
+            Acteur::Critibot => None
+        };
+        let marques: Vec<String> = self.interesses.iter().map(Interet::description).collect();
+        self.historiser(acteur, Action::Critique, (!marques.is_empty()).then(|| marques.join(", ")), None);
+        /* Les marques des autres membres sont retirées sans avoir servi. */
+        let mut evenements: Vec<Evenement> = self.interesses.iter()
+            .filter(|interet| critique.is_none_or(|(id, nom)| !interet.appartient_a(id, nom)))
+            .map(|interet| Evenement::nouveau(self, interet.member, &interet.name, TypeEvenement::Liberation, None))
+            .collect();
+        if let Some((id, nom)) = critique {
+            let delai = self.interesses.iter().find(|interet| interet.appartient_a(id, nom))
+                .map(|interet| (Utc::now() - *interet.date).num_seconds());
+            evenements.push(Evenement::nouveau(self, id, nom, TypeEvenement::Critique, delai));
+        }
         self.last_update = Timestamp::now();
         self.delete_interet();
-        self.status = Status::EnAttente;
-        self.modified = true;
+        self.changer_statut(Status::EnAttente, acteur);
+        evenements
     }
 
     pub fn delete_interet(&mut self) {
@@ -74,25 +174,37 @@ impl Ecrit {
     }
 
 
-    fn _liberer(&mut self, index: Option<usize>) -> bool {
-        if let Some(index) = index {
-            self.interesses.remove(index);
-            if self.interesses.len() == 0 {
-                self.status = Status::Ouvert;
-            }
+    /// Retire la marque d’intérêt à l’index donné. Renvoie l’événement de statistiques de la
+    /// libération, ou `None` s’il n’y a pas de marque à retirer.
+    fn _liberer(&mut self, index: Option<usize>, acteur: &Acteur) -> Option<Evenement> {
+        let interet = self.interesses.remove(index?);
+        self.historiser(acteur, Action::RetraitMarque, Some(interet.description()), None);
+        let evenement = Evenement::nouveau(self, interet.member, &interet.name, TypeEvenement::Liberation, None);
+        if self.interesses.len() == 0 {
+            self.changer_statut(Status::Ouvert, acteur);
         }
-        index.is_some()
+        Some(evenement)
     }
 
-    pub fn liberer_id(&mut self, membre: u64) -> bool {
+    pub fn liberer_id(&mut self, membre: u64, acteur: &Acteur) -> Option<Evenement> {
         if membre == 0 {  /*  Étant donné qu'il peut exister des réservations à l'identifiant zéro, */
-            return false; /*  ce sont les réservations faites pour un autre utilisateur             */
+            return None;  /*  ce sont les réservations faites pour un autre utilisateur             */
         }
-        self._liberer(self.interesses.iter().position(|interet| interet.member == membre))
+        self._liberer(self.interesses.iter().position(|interet| interet.member == membre), acteur)
     }
 
-    pub fn liberer_name(&mut self, membre: &String) -> bool {
-        self._liberer(self.interesses.iter().position(|interet| interet.name == *membre))
+    pub fn liberer_name(&mut self, membre: &String, acteur: &Acteur) -> Option<Evenement> {
+        self._liberer(self.interesses.iter().position(|interet| interet.name == *membre), acteur)
+    }
+
+    /// Enregistre la base de données avec la version de son format. Avec la fonctionnalité `sqlite`, les
+    /// changements sont aussi écrits dans la base SQLite.
+    pub fn sauvegarder(bot: &mut Bot<Self>) -> Result<(), ErrType> {
+        bot.save()?;
+        migrations::inscrire_version(crate::CHEMIN_BASE)?;
+        #[cfg(feature = "sqlite")]
+        Self::enregistrer_sqlite(&bot.database);
+        Ok(())
     }
 
     pub fn find_id(url: &String) -> Option<u64> {
@@ -102,12 +214,56 @@ impl Ecrit {
             )
     }
 
-    pub fn marquer(&mut self, interet: Interet) {
-        self.liberer_id(interet.member);
-        self.liberer_name(&interet.name);
+    /// Un écrit peut recevoir une marque d’intérêt s’il est ouvert, marqué ou non.
+    pub fn peut_etre_marque(&self) -> bool {
+        self.status == Status::OuvertPlus || self.status.transition(&Status::OuvertPlus, &self.type_).is_ok()
+    }
+
+    /// Retire les marques immédiates expirées. Renvoie les événements de statistiques des libérations.
+    pub fn retirer_marques_expirees(&mut self) -> Vec<Evenement> {
+        let mut evenements = Vec::new();
+        while let Some(index) = self.interesses.iter().position(Interet::expiree) {
+            evenements.extend(self._liberer(Some(index), &Acteur::Critibot));
+        }
+        evenements
+    }
+
+    /// Vérifie qu’aucune marque exclusive d’un autre membre ne réserve l’écrit.
+    pub fn verifier_reservation(&self, membre: u64, nom: &str) -> Result<(), String> {
+        match self.interesses.iter()
+            .find(|interet| !interet.appartient_a(membre, nom) && interet.fin_reservation().is_some()) {
+            Some(interet) => Err(format!("L’écrit « {} » est réservé par {} jusqu’au {} (marque exclusive).",
+                                         self.nom, interet.name,
+                                         interet.fin_reservation().unwrap().format("%d/%m/%Y à %H:%M"))),
+            None => Ok(())
+        }
+    }
+
+    /// Ajoute une marque d’intérêt, ou renvoie la raison pour laquelle elle est refusée. Renvoie les
+    /// événements de statistiques de la marque, à enregistrer une fois l’action effectuée.
+    pub fn marquer(&mut self, interet: Interet, acteur: &Acteur) -> Result<Vec<Evenement>, String> {
+        self.verifier_reservation(interet.member, &interet.name)?;
+        if interet.type_ == InteretType::Exclusif {
+            /* Les marques immédiates expirées sont retirées ci-dessous. */
+            let autres: Vec<&str> = self.interesses.iter()
+                .filter(|autre| !autre.appartient_a(interet.member, &interet.name) && !autre.expiree())
+                .map(|autre| autre.name.as_str())
+                .collect();
+            if !autres.is_empty() {
+                return Err(format!("L’écrit « {} » est déjà marqué par {} : il ne peut plus être réservé.",
+                                   self.nom, autres.join(", ")));
+            }
+        }
+        let mut evenements = self.retirer_marques_expirees();
+        /* La nouvelle marque remplace celle que la même personne aurait déjà posée. */
+        self.interesses.retain(|autre|
+            (interet.member == 0 || autre.member != interet.member) && autre.name != interet.name
+        );
+        self.historiser(acteur, Action::Marque, None, Some(interet.description()));
+        evenements.push(Evenement::nouveau(self, interet.member, &interet.name, TypeEvenement::Marque, None));
         self.interesses.push(interet);
-        self.status = Status::OuvertPlus;
-        self.modified = true;
+        self.changer_statut(Status::OuvertPlus, acteur);
+        Ok(evenements)
     }
 
     pub fn liste_auteurs(database: &HashMap<u64, Self>) -> Vec<&String> {
@@ -130,6 +286,32 @@ impl Ecrit {
         }).collect()
     }
 
+    /// Vrai si l’écrit possède le tag, sans tenir compte de la casse ni des accents.
+    pub fn possede_tag(&self, tag: &str) -> bool {
+        let tag = basicize(tag);
+        self.tags.iter().any(|tag_ecrit| basicize(tag_ecrit) == tag)
+    }
+
+    /// Vrai si l’écrit possède les tags demandés selon le mode. Une liste vide accepte tous les écrits.
+    pub fn correspond_tags(&self, tags: &[String], mode: ModeTags) -> bool {
+        tags.is_empty() || match mode {
+            ModeTags::Tous => tags.iter().all(|tag| self.possede_tag(tag)),
+            ModeTags::Un => tags.iter().any(|tag| self.possede_tag(tag)),
+            ModeTags::Aucun => !tags.iter().any(|tag| self.possede_tag(tag)),
+        }
+    }
+
+    /// Écrits ouverts du type demandé (tous si `None`), du plus anciennement modifié au plus récent.
+    pub fn plus_anciens(bot: &Bot<Self>, type_: Option<Type>) -> Vec<&u64> {
+        let mut candidats = Self::ulister(bot, "".to_string(), vec![Status::Ouvert],
+                                          type_.map(|type_| vec![type_]).unwrap_or_default(),
+                                          Vec::new(), Vec::new(), ModeTags::Un, None, None, None);
+        candidats.sort_by_key(|id| bot.database.get(id).unwrap().last_update);
+        candidats
+    }
+
+    /// Écrits correspondant à tous les filtres donnés. Un filtre vide ou absent accepte tous les
+    /// écrits ; `marqueur` ne garde que les écrits portant une marque de ce membre.
     pub fn ulister<'a>(
         bot: &'a Bot<Self>,
         critere: String,
@@ -137,9 +319,10 @@ impl Ecrit {
         types: Vec<Type>,
         authors: Vec<&'a String>,
         tags: Vec<String>,
-        tags_et: bool,
+        mode_tags: ModeTags,
         modifie_avant: Option<Timestamp>,
-        modifie_apres: Option<Timestamp>
+        modifie_apres: Option<Timestamp>,
+        marqueur: Option<u64>
     ) -> Vec<&'a u64> {
         if critere.is_empty() {
             bot.database.keys().collect()
@@ -150,14 +333,10 @@ impl Ecrit {
             .filter(|ecrit| status.contains(&ecrit.status) || status.is_empty())
             .filter(|ecrit| types.contains(&ecrit.type_) || types.is_empty())
             .filter(|ecrit| authors.contains(&&ecrit.auteur) || authors.is_empty())
-            .filter(|ecrit| {
-                let tag_list: Vec<bool> = ecrit.tags.iter().map(|tag| {tags.contains(tag)}).collect();
-                tag_list.is_empty() ||
-                (!tags_et && tag_list.contains(&true)) ||
-                (tags_et && tag_list.into_iter().fold(true, |accumulator, has_tag| {accumulator && has_tag}))
-            })
+            .filter(|ecrit| ecrit.correspond_tags(&tags, mode_tags))
             .filter(|ecrit| modifie_avant.is_none() || ecrit.last_update < modifie_avant.unwrap())
             .filter(|ecrit| modifie_apres.is_none() || ecrit.last_update > modifie_apres.unwrap())
+            .filter(|ecrit| marqueur.is_none_or(|membre| ecrit.interesses.iter().any(|interet| interet.member == membre)))
             .map(|ecrit| &ecrit.id)
             .collect()
     }
@@ -178,6 +357,9 @@ impl Object for Ecrit {
             tags: Vec::new(),
             id: 0,
             discord_chan: None,
+            flux: None,
+            historique: Vec::new(),
+            membre_auteur: None,
         }
     }
 
@@ -193,28 +375,31 @@ impl Object for Ecrit {
             status: Status::from_str(data_hash["status"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un status.".to_string()))?)?,
             type_: Type::from_str(data_hash["type"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un type.".to_string()))?)?,
             auteur: data_hash["auteur"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un auteur.".to_string()))?.to_string(),
-            interesses: data_hash["interesses"].as_vec().ok_or(ErrType::YamlParseError("Erreur de yaml dans un interesses.".to_string()))?.iter().map(
-                |interet| -> Interet {
-                    Interet {
-                        name: interet["name"].as_str().unwrap().to_string(),
-                        date: Timestamp::from_unix_timestamp(interet["date"].as_i64().unwrap().try_into().unwrap()).unwrap(),
-                        type_: interet["type"].as_str().unwrap().to_string(),
-                        member: interet["member"].as_i64().unwrap().try_into().unwrap(),
-                    }
-                }
-            ).collect(),
-            modified: data_hash["edited"].as_bool().ok_or(ErrType::YamlParseError("Erreur de yaml dans un edited.".to_string()))?,
-            tags: data_hash["tags"].as_vec().ok_or(ErrType::YamlParseError("Erreur de yaml dans un status.".to_string()))?.iter().map(
-                |tag| -> String {
-                    tag.as_str().unwrap().to_string()
-                }
-            ).collect(),
-            last_update: Timestamp::from_unix_timestamp(data["lastUpdate"].as_i64()
+            interesses: match &data_hash["interesses"] {
+                Yaml::BadValue | Yaml::Null => Vec::new(),
+                Yaml::Array(interesses) => interesses.iter().map(Interet::from_yaml).collect::<Result<Vec<_>, ErrType>>()?,
+                _ => return Err(ErrType::YamlParseError("Erreur de yaml dans un interesses.".to_string()))
+            },
+            modified: data_hash["edited"].as_bool().unwrap_or(false),
+            tags: match &data_hash["tags"] {
+                Yaml::BadValue | Yaml::Null => Vec::new(),
+                Yaml::Array(tags) => tags.iter().map(|tag| tag.as_str().map(|tag| tag.to_string())
+                    .ok_or(ErrType::YamlParseError("Erreur de yaml dans un tag.".to_string())))
+                    .collect::<Result<Vec<_>, ErrType>>()?,
+                _ => return Err(ErrType::YamlParseError("Erreur de yaml dans un tags.".to_string()))
+            },
+            last_update: Timestamp::from_unix_timestamp(data_hash["last-update"].as_i64()
                 .ok_or(ErrType::YamlParseError("Erreur de yaml dans un last_update.".to_string()))?.try_into()?)?,
-            id: Ecrit::find_id(&lien).ok_or(ErrType::NoneError)?,
+            id: Ecrit::find_id(&lien).ok_or(ErrType::YamlParseError(format!("Lien sans identifiant de fil : {lien}")))?,
             lien,
             discord_chan: data_hash["discord-channel"].as_i64()
-                .map(|id| ChannelId::new(id.unsigned_abs()))
+                .map(|id| ChannelId::new(id.unsigned_abs())),
+            flux: data_hash["flux"].as_str().map(|flux| flux.to_string()),
+            historique: match data_hash["historique"].as_vec() {
+                Some(historique) => historique.iter().map(EntreeHistorique::from_yaml).collect::<Result<Vec<_>, ErrType>>()?,
+                None => Vec::new()
+            },
+            membre_auteur: data_hash["membre-auteur"].as_i64().map(|membre| membre.unsigned_abs())
         })
     }
 
@@ -224,7 +409,7 @@ impl Object for Ecrit {
         yaml_out.insert(Yaml::String("lien".to_string()), Yaml::String(self.lien.clone()));
         yaml_out.insert(Yaml::String("type".to_string()), Yaml::String(self.type_.to_string()));
         yaml_out.insert(Yaml::String("status".to_string()), Yaml::String(self.status.to_string()));
-        yaml_out.insert(Yaml::String("lastUpdate".to_string()), Yaml::Integer(self.last_update.timestamp()));
+        yaml_out.insert(Yaml::String("last-update".to_string()), Yaml::Integer(self.last_update.timestamp()));
         yaml_out.insert(Yaml::String("auteur".to_string()), Yaml::String(self.auteur.clone()));
         yaml_out.insert(Yaml::String("edited".to_string()), Yaml::Boolean(self.modified.clone()));
         let array_interet =
@@ -232,8 +417,14 @@ impl Object for Ecrit {
                 let mut hash_interet = yaml::Hash::new();
                 hash_interet.insert(Yaml::String("name".to_string()), Yaml::String(interet.name.to_string()));
                 hash_interet.insert(Yaml::String("date".to_string()), Yaml::Integer(interet.date.timestamp()));
-                hash_interet.insert(Yaml::String("type".to_string()), Yaml::String(interet.type_.to_string()));
+                hash_interet.insert(Yaml::String("type".to_string()), Yaml::String(interet.type_.cle().to_string()));
                 hash_interet.insert(Yaml::String("member".to_string()), Yaml::Integer(interet.member as i64));
+                if let Some(rappel) = interet.rappel {
+                    hash_interet.insert(Yaml::String("rappel".to_string()), Yaml::Integer(rappel.timestamp()));
+                }
+                if let Some(prolongee) = interet.prolongee {
+                    hash_interet.insert(Yaml::String("prolongee".to_string()), Yaml::Integer(prolongee.timestamp()));
+                }
                 Yaml::Hash(hash_interet)
             }).collect::<Vec<Yaml>>());
         yaml_out.insert(Yaml::String("interesses".to_string()), Yaml::Array(array_interet));
@@ -247,6 +438,17 @@ impl Object for Ecrit {
         if let Some(chan_id) = self.discord_chan {
             yaml_out.insert(Yaml::String("discord-channel".to_string()), Yaml::Integer(chan_id.get() as i64));
         }
+        if let Some(flux) = &self.flux {
+            yaml_out.insert(Yaml::String("flux".to_string()), Yaml::String(flux.clone()));
+        }
+        if !self.historique.is_empty() {
+            yaml_out.insert(Yaml::String("historique".to_string()), Yaml::Array(
+                self.historique.iter().map(EntreeHistorique::serialize).collect()
+            ));
+        }
+        if let Some(membre) = self.membre_auteur {
+            yaml_out.insert(Yaml::String("membre-auteur".to_string()), Yaml::Integer(membre as i64));
+        }
         Yaml::Hash(yaml_out)
     }
 
@@ -265,8 +467,12 @@ impl Object for Ecrit {
         ];
 
         if self.status == Status::OuvertPlus {
-            let interets_list = self.interesses.iter().map(|interet|
-               format!("{} par {} le {}\n", interet.type_, interet.name, interet.date.format("%d %B %Y à %H:%M"))
+            let interets_list = self.interesses.iter().filter(|interet| !interet.expiree()).map(|interet|
+               match interet.fin_reservation() {
+                   Some(fin) => format!("{} par {} le {}, réservé jusqu’au {}\n", interet.type_, interet.name,
+                                        interet.date.format("%d %B %Y à %H:%M"), fin.format("%d %B %Y à %H:%M")),
+                   None => format!("{} par {} le {}\n", interet.type_, interet.name, interet.date.format("%d %B %Y à %H:%M"))
+               }
             ).reduce(|str_total, str_current| str_total + str_current.as_str());
             if let Some(interets_list) = interets_list {
                 fields.push(("Marques d’intérêt", interets_list, false));
@@ -339,20 +545,71 @@ impl Object for Ecrit {
     }
 
     fn up(&mut self) {
-        if self.status != Status::OuvertPlus {
-            self.status = Status::Ouvert;
-        }
+        self.status = if self.interesses.is_empty() {
+            Status::Ouvert
+        } else {
+            Status::OuvertPlus
+        };
     }
 
     async fn buttons(ctx: &SerenityContext, interaction: &mut ComponentInteraction, bot: &mut Bot<Self>) -> Result<(), ErrType> {
         let parts: Vec<&str> = interaction.data.custom_id.split("-").collect();
         let button_type = *parts.get(0)
             .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?;
+        /* Les boutons sont soumis aux mêmes permissions que les commandes. */
+        let roles = interaction.member.as_ref().map(|member| member.roles.clone()).unwrap_or_default();
+        if !permissions::accorde(&roles, permissions::bouton(button_type, parts.get(2).copied())) {
+            interaction.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content("Vous n'avez pas la permission d'utiliser ce bouton.").ephemeral(true))).await?;
+            return Ok(());
+        }
         match button_type {
-            "e" => {
+            /* « ok » : action destructrice confirmée depuis le message de confirmation. */
+            "e" | "ok" => {
                 let id: u64 = parts.get(1)
                     .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?.parse()?;
                 let action = *parts.get(2).ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?;
+                /* Les boutons changeant le statut doivent respecter les transitions autorisées. */
+                if let Some(ecrit) = bot.database.get(&id) {
+                    let cible = match action {
+                        "c" => Some(Status::EnAttente),
+                        "r" => Some(Status::Refuse),
+                        "u" => Some(Status::Ouvert),
+                        "p" => Some(Status::Publie),
+                        _ => None
+                    };
+                    let refus = cible.and_then(|cible| match ecrit.status.transition(&cible, &ecrit.type_) {
+                        Ok(permission) if permissions::accorde(&roles, permission) => None,
+                        Ok(_) => Some(format!("Vous n’avez pas la permission de passer un écrit au statut {cible}.")),
+                        Err(raison) => Some(raison)
+                    });
+                    if let Some(raison) = refus {
+                        interaction.create_response(ctx, CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new().content(raison).ephemeral(true))).await?;
+                        return Ok(());
+                    }
+                    /* Une marque exclusive empêche les autres membres de critiquer l’écrit. */
+                    if action == "c" {
+                        let member = interaction.member.as_ref().unwrap();
+                        if let Err(raison) = ecrit.verifier_reservation(member.user.id.get(), member.nick.as_ref().unwrap_or(&member.user.name)) {
+                            interaction.create_response(ctx, CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new().content(raison).ephemeral(true))).await?;
+                            return Ok(());
+                        }
+                    }
+                }
+                /* Les actions destructrices demandent une confirmation. */
+                if button_type == "e" && (action == "c" || action == "r") {
+                    let ecrit = bot.database.get(&id).ok_or(ErrType::ObjectNotFound(id.to_string()))?;
+                    return Self::demander_confirmation(ctx, interaction, ecrit, action).await;
+                }
+                if !bot.database.contains_key(&id) {
+                    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+                    return Err(ErrType::ObjectNotFound(id.to_string()));
+                }
+                /* Les statistiques et la notification de l’auteur attendent que l’action soit enregistrée. */
+                let mut evenements = Vec::new();
+                let mut notification: Option<fn(&Self, &Acteur)> = None;
                 match action {
                     "m" => {
                         interaction.create_response(ctx, CreateInteractionResponse::Message(
@@ -360,33 +617,37 @@ impl Object for Ecrit {
                                 .components(vec![Interet::action_row(id)]).ephemeral(true))).await?;
                     }
                     "c" => {
-                        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
-                        bot.log(&ctx, format!("{} a marqué {} (id: {id}) comme critiqué.",
-                            tools::user_desc(&interaction.user),
-                            bot.database.get(&id).unwrap().get_name()
-                        )).await?;
                         bot.archive(vec![id]);
-                        bot.database.get_mut(&id).unwrap()/* Error check already done above */.critique();
+                        let avant = bot.database.get(&id).unwrap().clone();
+                        evenements = bot.database.get_mut(&id).unwrap()/* Error check already done above */.critique(&Acteur::from(&interaction.user));
+                        notification = Some(Self::notifier_critique);
+                        let resultat = format!("Écrit « {} » marqué comme critiqué.", avant.nom);
+                        /* Discord attend la réponse dans les trois secondes : le journal passe après. */
+                        let journal = format!("{} a marqué {} (id: {id}) comme critiqué.", tools::user_desc(&interaction.user), avant.nom);
+                        Self::proposer_annulation(ctx, interaction, bot, avant, resultat).await?;
+                        Self::journaliser(ctx, bot, journal).await;
                     }
                     "r" => {
-                        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
-                        bot.log(&ctx, format!("{} a refusé {} (id: {id}).",
-                                              tools::user_desc(&interaction.user),
-                                              bot.database.get(&id).unwrap().get_name()
-                        )).await?;
                         bot.archive(vec![id]);
-                        bot.database.get_mut(&id).unwrap()/* Error check already done above */.status = Status::Refuse;
+                        let avant = bot.database.get(&id).unwrap().clone();
+                        bot.database.get_mut(&id).unwrap()/* Error check already done above */.changer_statut(Status::Refuse, &Acteur::from(&interaction.user));
+                        notification = Some(Self::notifier_statut);
+                        let resultat = format!("Écrit « {} » refusé.", avant.nom);
+                        let journal = format!("{} a refusé {} (id: {id}).", tools::user_desc(&interaction.user), avant.nom);
+                        Self::proposer_annulation(ctx, interaction, bot, avant, resultat).await?;
+                        Self::journaliser(ctx, bot, journal).await;
                     }
                     "d" => {
                         interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
                         if bot.database.contains_key(&id) {
                             bot.archive(vec![id]);
-                            bot.database.get_mut(&id).unwrap()
-                                .liberer_name(interaction.member.as_ref().unwrap().nick.as_ref().unwrap_or(&interaction.member.as_ref().unwrap().user.name));
+                            let liberation = bot.database.get_mut(&id).unwrap()
+                                .liberer_name(interaction.member.as_ref().unwrap().nick.as_ref().unwrap_or(&interaction.member.as_ref().unwrap().user.name), &Acteur::from(&interaction.user));
                             bot.log(&ctx, format!("{} a libéré sa marque sur l'écrit {} (id: {id}).",
                                                   tools::user_desc(&interaction.user),
                                                   bot.database.get(&id).unwrap().get_name()
                             )).await?;
+                            evenements.extend(liberation);
                         } else {
                             return Err(ErrType::ObjectNotFound(id.to_string()));
                         }
@@ -395,8 +656,7 @@ impl Object for Ecrit {
                         interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
                         if bot.database.contains_key(&id) {
                             bot.archive(vec![id]);
-                            bot.database.get_mut(&id).unwrap().status = Status::Ouvert;
-                            bot.database.get_mut(&id).unwrap().modified = true;
+                            bot.database.get_mut(&id).unwrap().up_par(&Acteur::from(&interaction.user));
                             bot.log(&ctx, format!("{} a up {} (id: {id}).",
                                                   tools::user_desc(&interaction.user),
                                                   bot.database.get(&id).unwrap().get_name()
@@ -409,8 +669,7 @@ impl Object for Ecrit {
                         interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
                         if bot.database.contains_key(&id) {
                             bot.archive(vec![id]);
-                            bot.database.get_mut(&id).unwrap().status = Status::Publie;
-                            bot.database.get_mut(&id).unwrap().modified = true;
+                            bot.database.get_mut(&id).unwrap().changer_statut(Status::Publie, &Acteur::from(&interaction.user));
                             bot.log(&ctx, format!("{} a marqué {} (id: {id}) comme publié.",
                                                   tools::user_desc(&interaction.user),
                                                   bot.database.get(&id).unwrap().get_name()
@@ -424,38 +683,83 @@ impl Object for Ecrit {
                         eprintln!("Action inconnue pressée sur un bouton: e-{id}-{action}");
                     }
                 }
-                let ecrit = bot.database.get(&id);
-                interaction.message.edit(ctx, EditMessage::new().embed(
-                    ecrit.ok_or(ErrType::ObjectNotFound(id.to_string()))?.get_embed()
-                ).components(vec![ecrit.unwrap().get_buttons()])).await?;
+                /* Après confirmation, le message du bouton est celui de la confirmation et non l’embed de l’écrit. */
+                if button_type == "e" {
+                    let ecrit = bot.database.get(&id);
+                    interaction.message.edit(ctx, EditMessage::new().embed(
+                        ecrit.ok_or(ErrType::ObjectNotFound(id.to_string()))?.get_embed()
+                    ).components(vec![ecrit.unwrap().get_buttons()])).await?;
+                }
                 bot.update_affichans(ctx).await?;
-                bot.save()?;
+                Self::sauvegarder(bot)?;
+                statistiques::enregistrer(evenements);
+                if let (Some(notifier), Some(ecrit)) = (notification, bot.database.get(&id)) {
+                    notifier(ecrit, &Acteur::from(&interaction.user));
+                }
             }
             "tm" => {
                 let id: u64 = parts.get(1)
                     .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?.parse()?;
-                let type_ = *parts.get(2).ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?;
-                if bot.database.contains_key(&id) {
+                let type_ = parts.get(2).and_then(|cle| InteretType::from_cle(cle))
+                    .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?;
+                if bot.database.get(&id).is_some_and(|ecrit| !ecrit.peut_etre_marque()) {
+                    interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new().content("Cet écrit n’est pas ouvert à la critique.").components(vec![]).ephemeral(true))).await?;
+                } else if bot.database.contains_key(&id) {
                     bot.archive(vec![id]);
                     let ecrit = bot.database.get_mut(&id).unwrap();
                     let member = interaction.member.as_ref().unwrap();
-                    ecrit.marquer(Interet {
+                    let evenements = match ecrit.marquer(Interet {
                         name: interaction.member.as_ref().unwrap().nick.as_ref().unwrap_or(&interaction.member.as_ref().unwrap().user.name).clone(),
                         date: Timestamp::now(),
-                        type_: Interet::get_type(type_).to_string(),
+                        type_,
                         member: member.user.id.get(),
-                    });
+                        rappel: None,
+                        prolongee: None,
+                    }, &Acteur::from(&interaction.user)) {
+                        Ok(evenements) => evenements,
+                        Err(refus) => {
+                            interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+                                CreateInteractionResponseMessage::new().content(refus).components(vec![]).ephemeral(true))).await?;
+                            return Ok(());
+                        }
+                    };
                     interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
                         CreateInteractionResponseMessage::new().content("Écrit marqué.").components(vec![]).ephemeral(true))).await?;
                     bot.log(&ctx, format!("{} a marqué son intérêt sur {} (id: {id}).",
                                           tools::user_desc(&interaction.user),
                                           bot.database.get(&id).unwrap().get_name()
                     )).await?;
+                    Self::sauvegarder(bot)?;
+                    statistiques::enregistrer(evenements);
+                    bot.database.get(&id).unwrap().notifier_marque();
                 } else {
                     interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
                     return Err(ErrType::ObjectNotFound(id.to_string()));
                 }
             }
+            "no" => {
+                interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().content("Aucune action effectuée.").components(vec![]))).await?;
+            }
+            "an" => {
+                let jeton: u64 = parts.get(1)
+                    .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?.parse()?;
+                Self::bouton_annuler(ctx, interaction, bot, jeton).await?;
+            }
+            "nv" | "nt" | "nc" => {
+                let jeton: u64 = parts.get(1)
+                    .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?.parse()?;
+                Self::composant_navigateur(ctx, interaction, bot, button_type, jeton, parts.get(2).copied()).await?;
+            }
+            "mm" | "ml" => {
+                Self::composant_tableau_marques(ctx, interaction, bot, button_type, &parts).await?;
+            }
+            "rm" => {
+                let id: u64 = parts.get(1)
+                    .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?.parse()?;
+                Self::bouton_conserver_marque(ctx, interaction, bot, id).await?;
+            }
             _ => { interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?; }
         }
 
@@ -463,95 +767,14 @@ impl Object for Ecrit {
     }
 
     async fn maj_rss(bot: &DataType<Self>) -> Result<(), ErrType> {
-        let url = "http://fondationscp.wikidot.com/feed/forum/ct-656675.xml";
-        let regex_balises = Regex::new(r##"\s*\[([^\[]*)]"##)?;
-        /* OH FUCK */
-        let regex_titres = Regex::new(r##"(?i)\s*(?:\s*[\[(][^\[]*?[])][\s/\\\-]*)*(?:scp(?:[-\s][\dXY#█?]+(?:[-\s]fr)?)?)?[\s:\-"]*([^"]*?(?:"[^"]+"?[^"]*?)*)[\s".]*(?:\(.*(?:provisoire|temporaire|version).*\))?[\s".]*$"##)?;
-        let bot = &mut bot.lock().await;
-        let rss = Channel::read_from(&reqwest::get(url).await?.bytes().await?[..])?;
-        /* Copie étant donné qu'elle ne coûte pas grand chose par rapport à la difficulté que ce serait
-         * de l'éviter. */
-        let bot_last_rss_update = bot.last_rss_update.clone();
-
-        let last_date = rss.items.into_iter()
-            .filter_map(|entry| match DateTime::parse_from_rfc2822(entry.pub_date.as_ref().unwrap().as_str()) {
-                Ok(date) => if date.to_utc() > bot_last_rss_update {
-                    Some((date.to_utc(), entry))
-                } else {None},
-                Err(_) => {
-                    eprintln!("Erreur lors de la récupération des flux RSS : pas de date.");
-                    None
-                }
-            })
-            .filter(|(_, entry)| entry.title.as_ref().is_some_and(|str| { str.contains("]") }))
-            .filter_map(|(date, mut entry)| {
-                let type_ = regex_balises.captures_iter(entry.title.as_ref().unwrap())
-                    .map(|balise| balise.extract::<1>().0.trim().to_lowercase())
-                    .fold(Type::Rapport, |type_, balise|
-                        if balise.contains("idée") || balise.contains("idee") {
-                            Type::Idee
-                        } else if balise.contains("conte") || balise.contains("série") || balise.contains("serie") {
-                            Type::Conte
-                        } else if balise.contains("format") {
-                            Type::FormatGdi
-                        } else {
-                            type_
-                        }
-                    );
-
-                let title = entry.title.as_ref().and_then(|entry_title|
-                    regex_titres.captures(entry_title.as_str())
-                        .and_then(|capture| capture.extract::<1>().1.to_vec().pop())
-                );
-
-                let lien = take(&mut entry.link);
-
-                let auteur = entry.extensions().get("wikidot")
-                    .and_then(|wikidot| wikidot.get("authorName")
-                        .and_then(|author_name| author_name.get(0)
-                            .and_then(|author_name| author_name.value())
-                        )
-                    );
-
-                if title.is_none() || lien.is_none() || auteur.is_none() {
-                    eprintln!("L'une des données d'une entrée RSS (titre, line ou auteur) est incorrecte.");
-                    return None;
-                }
-                let (title, lien, auteur) = (title.unwrap().to_string(), lien.unwrap(), auteur.unwrap().to_string());
-
-                let id = Ecrit::find_id(&lien);
-                if id.is_none() {
-                    eprintln!("Lien malformé dans une entrée RSS : impossible de récupérer l'ID.");
-                    return None;
-                }
-                let id = id.unwrap();
-
-                Some((date, Ecrit {
-                    status: Status::Ouvert,
-                    type_,
-                    nom: title,
-                    lien,
-                    last_update: Timestamp::now(),
-                    auteur,
-                    interesses: vec![],
-                    modified: false,
-                    tags: vec![],
-                    id,
-                    discord_chan: None
-                }))
-            }).map(|(date, ecrit)| {
-            if bot.database.contains_key(&ecrit.id) {
-                eprintln!("Ajout RSS d’un écrit déjà ajouté. Informations : écrit [{}] - last_rss_update [{}] - date>last_rss_update [{}]", date, bot.last_rss_update, date > bot.last_rss_update);
-            } else {
-                bot.database.insert(ecrit.id, ecrit);
-            }
-            date
-        }).max();
-
-        if let Some(last_date) = last_date {
-            bot.last_rss_update = max(last_date, bot.last_rss_update);
-            bot.update_affichans = true;
+        /* La bibliothèque enregistre la base après chaque mise à jour, sans la version de son format. */
+        migrations::inscrire_version(crate::CHEMIN_BASE)?;
+        Self::maj_flux(bot).await?;
+        if config::get().suivi_fils {
+            Self::suivre_fils(bot).await?;
         }
+        #[cfg(feature = "sqlite")]
+        Self::enregistrer_sqlite(&bot.lock().await.database);
         Ok(())
     }
 
@@ -562,4 +785,49 @@ impl Object for Ecrit {
     fn set_date(&mut self, t: Timestamp) {
         self.last_update = t;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ecrit_avec_tags(tags: &[&str]) -> Ecrit {
+        let mut ecrit = Ecrit::new("Le Mur".to_string(), "http://fondationscp.wikidot.com/forum/t-1/".to_string(),
+                                   Type::Rapport, Status::Ouvert, "Dr Foo".to_string()).unwrap();
+        ecrit.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        ecrit
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn mode_tous() {
+        let ecrit = ecrit_avec_tags(&["Humanoïde", "Euclide"]);
+        assert!(ecrit.correspond_tags(&tags(&["humanoide", "EUCLIDE"]), ModeTags::Tous));
+        assert!(!ecrit.correspond_tags(&tags(&["humanoïde", "humour"]), ModeTags::Tous));
+    }
+
+    #[test]
+    fn mode_un() {
+        let ecrit = ecrit_avec_tags(&["Humanoïde", "Euclide"]);
+        assert!(ecrit.correspond_tags(&tags(&["humour", "euclide"]), ModeTags::Un));
+        assert!(!ecrit.correspond_tags(&tags(&["humour", "sûr"]), ModeTags::Un));
+    }
+
+    #[test]
+    fn mode_aucun() {
+        let ecrit = ecrit_avec_tags(&["Humanoïde", "Euclide"]);
+        assert!(ecrit.correspond_tags(&tags(&["humour", "sûr"]), ModeTags::Aucun));
+        assert!(!ecrit.correspond_tags(&tags(&["humour", "Euclide"]), ModeTags::Aucun));
+        assert!(ecrit_avec_tags(&[]).correspond_tags(&tags(&["humour"]), ModeTags::Aucun));
+    }
+
+    #[test]
+    fn liste_vide_acceptee_par_tous_les_modes() {
+        let ecrit = ecrit_avec_tags(&[]);
+        for mode in [ModeTags::Tous, ModeTags::Un, ModeTags::Aucun] {
+            assert!(ecrit.correspond_tags(&[], mode));
+        }
+    }
+}
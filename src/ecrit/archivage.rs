@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use fondabots_lib::{DataType, ErrType};
+use poise::serenity_prelude as serenity;
+use serenity::all::{ChannelId, Context as SerenityContext, CreateMessage};
+
+use crate::config;
+use super::fields::Status;
+use super::historique::{Acteur, Action};
+use super::notifications::TypeNotification;
+use super::Ecrit;
+
+/// Intervalle entre deux vérifications de l’ancienneté des écrits.
+const INTERVALLE_ARCHIVAGE: Duration = Duration::from_secs(60 * 60);
+
+/// Message à poster dans le fil Discord d’un écrit.
+struct Annonce {
+    fil: ChannelId,
+    message: String,
+}
+
+impl Ecrit {
+    /// Date à laquelle l’écrit sera archivé automatiquement, s’il est ouvert et que son type a un âge
+    /// d’archivage configuré.
+    pub fn echeance_archivage(&self) -> Option<DateTime<Utc>> {
+        let archivage = config::get().archivage.as_ref()?;
+        if self.status != Status::Ouvert && self.status != Status::OuvertPlus {
+            return None;
+        }
+        archivage.age(&self.type_).map(|age| *self.last_update + age)
+    }
+
+    /// L’auteur a déjà été averti depuis la dernière mise à jour de l’écrit.
+    fn averti(&self) -> bool {
+        self.historique.iter().any(|entree| entree.action == Action::AvertissementArchivage && entree.date >= self.last_update)
+    }
+
+    /// Archive l’écrit s’il a atteint son échéance ou prépare l’avertissement de l’auteur si elle
+    /// approche. Renvoie le message à journaliser et l’annonce à poster dans le fil de l’écrit.
+    fn verifier_archivage(&mut self) -> (Option<String>, Option<Annonce>) {
+        let (Some(echeance), Some(archivage)) = (self.echeance_archivage(), config::get().archivage.as_ref()) else {
+            return (None, None);
+        };
+        let maintenant = Utc::now();
+        if echeance <= maintenant {
+            self.changer_statut(Status::SansNouvelles, &Acteur::Critibot);
+            let journal = format!("Écrit {} (id: {}) marqué sans nouvelles automatiquement : aucune mise à jour depuis le {}.",
+                                  self.nom, self.id, self.last_update.format("%d/%m/%Y"));
+            let annonce = self.discord_chan.map(|fil| Annonce {
+                fil,
+                message: format!("Faute de mise à jour depuis le {}, l’écrit « {} » est désormais « Sans nouvelles ». \
+                                  Postez un nouveau message pour le rouvrir à la critique.", self.last_update.format("%d/%m/%Y"), self.nom),
+            });
+            (Some(journal), annonce)
+        } else if echeance - archivage.avertissement <= maintenant && !self.averti() {
+            let date = echeance.format("%d/%m/%Y").to_string();
+            self.historiser(&Acteur::Critibot, Action::AvertissementArchivage, None, Some(date.clone()));
+            let annonce = self.discord_chan.map(|fil| Annonce {
+                fil,
+                message: format!("L’écrit « {} » n’a pas été mis à jour depuis le {}. Sans nouvelle activité, \
+                                  il passera « Sans nouvelles » le {date}.", self.nom, self.last_update.format("%d/%m/%Y")),
+            });
+            (None, annonce)
+        } else {
+            (None, None)
+        }
+    }
+
+    /// Archive les écrits ouverts trop anciens de toute la base de données et avertit les auteurs de
+    /// ceux qui le seront bientôt.
+    pub async fn entretenir_archivage(ctx: &SerenityContext, bot: &DataType<Self>) -> Result<(), ErrType> {
+        let annonces = {
+            let bot = &mut bot.lock().await;
+            let (journaux, annonces): (Vec<_>, Vec<_>) = bot.database.values_mut()
+                .map(|ecrit| {
+                    let (journal, annonce) = ecrit.verifier_archivage();
+                    (journal.map(|journal| (ecrit.id, journal)), annonce)
+                })
+                .unzip();
+            let (archives, journaux): (Vec<u64>, Vec<String>) = journaux.into_iter().flatten().unzip();
+            for journal in &journaux {
+                bot.log(ctx, journal.clone()).await?;
+            }
+            if !journaux.is_empty() {
+                bot.update_affichans(ctx).await?;
+            }
+            let annonces: Vec<Annonce> = annonces.into_iter().flatten().collect();
+            if !journaux.is_empty() || bot.database.values().any(|ecrit| ecrit.modified) {
+                Self::sauvegarder(bot)?;
+            }
+            for ecrit in archives.iter().filter_map(|id| bot.database.get(id)) {
+                ecrit.notifier(TypeNotification::Archivage, format!("faute de mise à jour depuis le {}, votre écrit est désormais « Sans nouvelles ».",
+                                                                     ecrit.last_update.format("%d/%m/%Y")));
+            }
+            annonces
+        };
+
+        /* Les messages sont envoyés sans garder le verrou sur le bot. */
+        for annonce in annonces {
+            if let Err(e) = annonce.fil.send_message(ctx, CreateMessage::new().content(annonce.message)).await {
+                eprintln!("Impossible de poster l’annonce d’archivage dans le fil {} : {e}", annonce.fil);
+            }
+        }
+        Ok(())
+    }
+
+    /// Vérifie régulièrement l’ancienneté des écrits. Lancée une seule fois, à la connexion du bot,
+    /// et sans effet si l’archivage automatique n’est pas configuré.
+    pub async fn surveiller_archivage(ctx: SerenityContext, bot: DataType<Self>) {
+        if config::get().archivage.is_none() {
+            return;
+        }
+        let mut horloge = tokio::time::interval(INTERVALLE_ARCHIVAGE);
+        loop {
+            horloge.tick().await;
+            if let Err(e) = Self::entretenir_archivage(&ctx, &bot).await {
+                eprintln!("Erreur lors de l’archivage automatique : {e}");
+            }
+        }
+    }
+}
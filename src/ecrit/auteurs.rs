@@ -0,0 +1,259 @@
+use std::fs;
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use fondabots_lib::tools::basicize;
+use fondabots_lib::yaml_rust2::{yaml, Yaml, YamlEmitter, YamlLoader};
+use fondabots_lib::{Bot, ErrType};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use super::notifications::Preferences;
+use super::Ecrit;
+use crate::config;
+
+/// Durée pendant laquelle un code de vérification peut être utilisé.
+const VALIDITE_CODE: TimeDelta = TimeDelta::hours(1);
+
+/// Membre Discord et noms wikidot sous lesquels il publie.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Auteur {
+    pub membre: u64,
+    /// Noms wikidot du membre, du plus ancien au plus récent : les anciens noms après un renommage et
+    /// les éventuels comptes secondaires y restent pour retrouver les écrits qui les portent.
+    pub noms: Vec<String>,
+    pub notifications: Preferences,
+}
+
+impl Auteur {
+    fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        let erreur = |champ: &str| ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ} du registre des auteurs."));
+        Ok(Self {
+            membre: data["membre"].as_i64().ok_or(erreur("membre"))?.unsigned_abs(),
+            noms: data["noms"].as_vec().ok_or(erreur("noms"))?.iter()
+                .map(|nom| nom.as_str().map(str::to_string).ok_or(erreur("noms")))
+                .collect::<Result<Vec<_>, _>>()?,
+            notifications: Preferences::from_yaml(&data["notifications"]),
+        })
+    }
+
+    fn serialize(&self) -> Yaml {
+        let mut hash = yaml::Hash::new();
+        hash.insert(Yaml::String("membre".to_string()), Yaml::Integer(self.membre as i64));
+        hash.insert(Yaml::String("noms".to_string()), Yaml::Array(self.noms.iter().cloned().map(Yaml::String).collect()));
+        hash.insert(Yaml::String("notifications".to_string()), self.notifications.serialize());
+        Yaml::Hash(hash)
+    }
+}
+
+/// Inscription en attente : le membre doit afficher le code sur son profil wikidot.
+struct Verification {
+    membre: u64,
+    nom: String,
+    code: String,
+    expiration: DateTime<Utc>,
+}
+
+static REGISTRE: Mutex<Vec<Auteur>> = Mutex::new(Vec::new());
+static VERIFICATIONS: Mutex<Vec<Verification>> = Mutex::new(Vec::new());
+
+/// Charge le registre des auteurs. Un registre absent est un registre vide.
+pub fn charger() -> Result<(), ErrType> {
+    let chemin = &config::get().auteurs;
+    let contenu = match fs::read_to_string(chemin) {
+        Ok(contenu) => contenu,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ErrType::YamlParseError(format!("{chemin} : {e}")))
+    };
+    let docs = YamlLoader::load_from_str(contenu.as_str())
+        .map_err(|e| ErrType::YamlParseError(format!("{chemin} illisible : {e}")))?;
+    let auteurs = match docs.first() {
+        Some(Yaml::Array(auteurs)) => auteurs.iter().map(Auteur::from_yaml).collect::<Result<Vec<_>, _>>()?,
+        _ => Vec::new()
+    };
+    *REGISTRE.lock().unwrap() = auteurs;
+    Ok(())
+}
+
+fn sauvegarder(registre: &[Auteur]) -> Result<(), ErrType> {
+    let chemin = &config::get().auteurs;
+    let mut sortie = String::new();
+    YamlEmitter::new(&mut sortie).dump(&Yaml::Array(registre.iter().map(Auteur::serialize).collect()))
+        .map_err(|e| ErrType::YamlParseError(format!("Impossible d’écrire le registre des auteurs : {e}")))?;
+    fs::write(chemin, sortie)
+        .map_err(|e| ErrType::YamlParseError(format!("Impossible d’écrire {chemin} : {e}")))
+}
+
+/// Membre Discord lié au nom wikidot, sans tenir compte de la casse ni des accents.
+pub fn membre(nom: &str) -> Option<u64> {
+    let nom = basicize(nom.trim());
+    REGISTRE.lock().unwrap().iter()
+        .find(|auteur| auteur.noms.iter().any(|autre| basicize(autre) == nom))
+        .map(|auteur| auteur.membre)
+}
+
+/// Entrée du registre d’un membre Discord, s’il y est inscrit.
+pub fn auteur(membre: u64) -> Option<Auteur> {
+    REGISTRE.lock().unwrap().iter().find(|auteur| auteur.membre == membre).cloned()
+}
+
+/// Lie un nom wikidot à un membre Discord. Le nom devient le nom actuel du membre, ses noms
+/// précédents restant liés. Refuse un nom déjà lié à un autre membre.
+pub fn lier(membre: u64, nom: &str) -> Result<(), String> {
+    let nom = nom.trim();
+    if nom.is_empty() {
+        return Err("Le nom wikidot ne peut pas être vide.".to_string());
+    }
+    let mut registre = REGISTRE.lock().unwrap();
+    let basique = basicize(nom);
+    if let Some(autre) = registre.iter().find(|auteur| auteur.membre != membre && auteur.noms.iter().any(|autre| basicize(autre) == basique)) {
+        return Err(format!("Le nom wikidot « {nom} » est déjà lié à <@{}>.", autre.membre));
+    }
+    match registre.iter_mut().find(|auteur| auteur.membre == membre) {
+        Some(auteur) => {
+            auteur.noms.retain(|autre| basicize(autre) != basique);
+            auteur.noms.push(nom.to_string());
+        }
+        None => registre.push(Auteur { membre, noms: vec![nom.to_string()], notifications: Preferences::default() })
+    }
+    sauvegarder(&registre).map_err(|e| e.to_string())
+}
+
+/// Retire un nom wikidot du registre. Renvoie le membre auquel il était lié.
+pub fn delier(nom: &str) -> Result<Option<u64>, ErrType> {
+    let basique = basicize(nom.trim());
+    let mut registre = REGISTRE.lock().unwrap();
+    let Some(auteur) = registre.iter_mut().find(|auteur| auteur.noms.iter().any(|autre| basicize(autre) == basique)) else {
+        return Ok(None);
+    };
+    let membre = auteur.membre;
+    auteur.noms.retain(|autre| basicize(autre) != basique);
+    registre.retain(|auteur| !auteur.noms.is_empty());
+    sauvegarder(&registre)?;
+    Ok(Some(membre))
+}
+
+/// Enregistre les préférences de notification d’un membre inscrit dans le registre des auteurs.
+/// Sans effet pour un membre qui n’y est pas inscrit.
+pub fn definir_notifications(membre: u64, preferences: Preferences) -> Result<(), ErrType> {
+    let mut registre = REGISTRE.lock().unwrap();
+    let Some(auteur) = registre.iter_mut().find(|auteur| auteur.membre == membre) else {
+        return Ok(());
+    };
+    auteur.notifications = preferences;
+    sauvegarder(&registre)
+}
+
+/// Adresse du profil wikidot d’un utilisateur, dont le nom est réduit comme le fait wikidot
+/// (minuscules, caractères non alphanumériques remplacés par des tirets).
+pub fn profil_wikidot(nom: &str) -> String {
+    let mut identifiant = String::new();
+    for caractere in basicize(nom.trim()).chars() {
+        if caractere.is_ascii_alphanumeric() {
+            identifiant.push(caractere.to_ascii_lowercase());
+        } else if !identifiant.is_empty() && !identifiant.ends_with('-') {
+            identifiant.push('-');
+        }
+    }
+    format!("https://www.wikidot.com/user:info/{}", identifiant.trim_end_matches('-'))
+}
+
+/// Commence l’inscription d’un membre sous un nom wikidot et renvoie le code qu’il doit afficher sur
+/// son profil. Un nouveau code remplace celui d’une inscription en cours.
+pub fn demander_verification(membre: u64, nom: &str) -> Result<String, String> {
+    let proprietaire = self::membre(nom);
+    if proprietaire == Some(membre) {
+        return Err(format!("Le nom wikidot « {} » vous est déjà lié.", nom.trim()));
+    }
+    if let Some(proprietaire) = proprietaire {
+        return Err(format!("Le nom wikidot « {} » est déjà lié à <@{proprietaire}>. Contactez l’équipe si c’est une erreur.", nom.trim()));
+    }
+    let code = format!("critibot-{}", rand::thread_rng().sample_iter(&Alphanumeric).take(10).map(char::from).collect::<String>());
+    let mut verifications = VERIFICATIONS.lock().unwrap();
+    let maintenant = Utc::now();
+    verifications.retain(|verification| verification.membre != membre && verification.expiration > maintenant);
+    verifications.push(Verification { membre, nom: nom.trim().to_string(), code: code.clone(), expiration: maintenant + VALIDITE_CODE });
+    Ok(code)
+}
+
+/// Termine l’inscription en cours d’un membre en cherchant le code sur son profil wikidot. Renvoie le
+/// nom lié, ou la raison de l’échec.
+pub async fn verifier(membre: u64) -> Result<String, String> {
+    let (nom, code) = VERIFICATIONS.lock().unwrap().iter()
+        .find(|verification| verification.membre == membre && verification.expiration > Utc::now())
+        .map(|verification| (verification.nom.clone(), verification.code.clone()))
+        .ok_or("Aucune inscription en cours, ou son code a expiré : recommencez avec `/inscription_auteur`.".to_string())?;
+    let profil = profil_wikidot(&nom);
+    let page = async { reqwest::get(&profil).await?.error_for_status()?.text().await }.await
+        .map_err(|e| format!("Impossible de lire le profil wikidot {profil} : {e}"))?;
+    if !page.contains(&code) {
+        return Err(format!("Le code `{code}` est introuvable sur le profil {profil}. Ajoutez-le dans la section « À propos » puis réessayez."));
+    }
+    lier(membre, &nom)?;
+    VERIFICATIONS.lock().unwrap().retain(|verification| verification.membre != membre);
+    Ok(nom)
+}
+
+impl Ecrit {
+    /// Cherche dans le registre des auteurs le membre Discord lié à l’auteur de l’écrit. Renvoie `true`
+    /// s’il a changé.
+    pub fn resoudre_auteur(&mut self) -> bool {
+        let membre = membre(&self.auteur);
+        if membre == self.membre_auteur {
+            return false;
+        }
+        self.membre_auteur = membre;
+        self.modified = true;
+        true
+    }
+
+    /// Met à jour le membre Discord de l’auteur de tous les écrits après un changement du registre.
+    /// Renvoie le nombre d’écrits modifiés.
+    pub fn resoudre_auteurs(bot: &mut Bot<Self>) -> usize {
+        bot.database.values_mut().map(Self::resoudre_auteur).filter(|&modifie| modifie).count()
+    }
+
+    /// Écrits dont l’auteur est lié au membre Discord, sous l’un quelconque de ses noms wikidot.
+    pub fn ecrits_du_membre(bot: &Bot<Self>, membre: u64) -> Vec<&u64> {
+        bot.database.values()
+            .filter(|ecrit| ecrit.membre_auteur == Some(membre))
+            .map(|ecrit| &ecrit.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecrit::fields::{Status, Type};
+    use crate::ecrit::notifications::{Destination, TypeNotification};
+
+    #[test]
+    fn membre_resolu_a_l_ajout_et_au_changement_d_auteur() {
+        REGISTRE.lock().unwrap().push(Auteur {
+            membre: 7,
+            noms: vec!["Dr Élan".to_string(), "Dr Elan 2".to_string()],
+            notifications: Preferences::default(),
+        });
+        let mut ecrit = Ecrit::new("Le Mur".to_string(), "http://fondationscp.wikidot.com/forum/t-1/".to_string(),
+                                   Type::Rapport, Status::Ouvert, "dr elan".to_string()).unwrap();
+        assert_eq!(ecrit.membre_auteur, Some(7));
+        assert!(!ecrit.resoudre_auteur());
+
+        ecrit.auteur = "Inconnu".to_string();
+        assert!(ecrit.resoudre_auteur());
+        assert_eq!(ecrit.membre_auteur, None);
+    }
+
+    #[test]
+    fn preferences_enregistrees_avec_l_auteur() {
+        let mut auteur = Auteur::from_yaml(&YamlLoader::load_from_str("membre: 12\nnoms: [Dr Foo]").unwrap()[0]).unwrap();
+        assert_eq!(auteur.notifications, Preferences::default());
+
+        auteur.notifications = Preferences {
+            destination: Destination::Fil,
+            evenements: vec![TypeNotification::Refus],
+        };
+        assert_eq!(Auteur::from_yaml(&auteur.serialize()).unwrap(), auteur);
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{DateTime, Datelike, Local, TimeDelta, Utc};
+use fondabots_lib::{Bot, DataType};
+use poise::serenity_prelude as serenity;
+use serenity::all::{Context as SerenityContext, CreateEmbed, CreateEmbedAuthor, CreateMessage, Timestamp};
+
+use crate::config::{self, BilanConfig};
+use super::fields::Status;
+use super::historique::Action;
+use super::statistiques::{self, TypeEvenement};
+use super::Ecrit;
+
+/// Nombre d’écrits cités au plus dans chaque section du bilan.
+const ELEMENTS_SECTION: usize = 10;
+
+/// Nombre d’écrits ouverts les plus anciens cités dans le bilan.
+const ANCIENS: usize = 5;
+
+/// Délai dans lequel une marque doit être retirée pour figurer dans le bilan.
+const EXPIRATION_PROCHE: TimeDelta = TimeDelta::weeks(1);
+
+/// Met en forme une section du bilan, en respectant la longueur maximale d’un champ d’embed.
+fn section(lignes: Vec<String>) -> String {
+    if lignes.is_empty() {
+        return "Aucun.".to_string();
+    }
+    let mut texte = String::new();
+    let mut citees = 0;
+    for ligne in lignes.iter().take(ELEMENTS_SECTION) {
+        if texte.chars().count() + ligne.chars().count() > 950 {
+            break;
+        }
+        texte += ligne;
+        texte += "\n";
+        citees += 1;
+    }
+    if citees < lignes.len() {
+        texte += &format!("… et {} autre(s).", lignes.len() - citees);
+    }
+    texte
+}
+
+/// Prochaine date de publication du bilan après `maintenant`.
+fn prochaine_publication(config: &BilanConfig, maintenant: DateTime<Local>) -> DateTime<Local> {
+    let jours = (7 + config.jour.num_days_from_monday() - maintenant.weekday().num_days_from_monday()) % 7;
+    let publication = (maintenant.date_naive() + TimeDelta::days(jours as i64))
+        .and_hms_opt(config.heure, 0, 0)
+        .and_then(|date| date.and_local_timezone(Local).earliest())
+        /* Heure inexistante à cause d’un changement d’heure : le bilan est publié une heure plus tard. */
+        .unwrap_or(maintenant + TimeDelta::hours(1));
+    if publication > maintenant {
+        publication
+    } else {
+        publication + TimeDelta::weeks(1)
+    }
+}
+
+impl Ecrit {
+    fn lien_markdown(&self) -> String {
+        format!("[{}]({})", self.nom, self.lien)
+    }
+
+    /// Bilan de l’activité entre deux dates : nouveaux écrits, critiques, validations et
+    /// publications, ainsi que les écrits ouverts les plus anciens et les marques bientôt retirées.
+    pub fn bilan(bot: &Bot<Self>, debut: Timestamp, fin: Timestamp) -> CreateEmbed {
+        let dans_periode = |date: &Timestamp| *date >= debut && *date < fin;
+
+        let nouveaux: Vec<String> = bot.database.values()
+            .filter(|ecrit| ecrit.historique.iter().any(|entree| entree.action == Action::Ajout && dans_periode(&entree.date)))
+            .map(|ecrit| format!("{} ({}, {})", ecrit.lien_markdown(), ecrit.auteur, ecrit.type_))
+            .collect();
+
+        /* Les critiques viennent des statistiques, qui gardent les écrits retirés de la base. */
+        let critiques: Vec<String> = statistiques::evenements(TypeEvenement::Critique, debut, fin).iter()
+            .map(|evenement| format!("{} par {}", bot.database.get(&evenement.ecrit)
+                .map_or(format!("Écrit {} (retiré de la base)", evenement.ecrit), Ecrit::lien_markdown), evenement.nom))
+            .collect();
+
+        let statuts_valides = [Status::Valide.to_string(), Status::Publie.to_string()];
+        let valides: Vec<String> = bot.database.values()
+            .filter_map(|ecrit| ecrit.historique.iter()
+                .filter(|entree| entree.action == Action::Statut && dans_periode(&entree.date))
+                .filter_map(|entree| entree.nouveau.as_ref())
+                .rfind(|statut| statuts_valides.contains(statut))
+                .map(|statut| format!("{} : {statut}", ecrit.lien_markdown())))
+            .collect();
+
+        let anciens: Vec<String> = Self::plus_anciens(bot, None).into_iter().take(ANCIENS)
+            .map(|id| bot.database.get(id).unwrap())
+            .map(|ecrit| format!("{}, modifié le {}", ecrit.lien_markdown(), ecrit.last_update.format("%d/%m/%Y")))
+            .collect();
+
+        let marques_config = &config::get().marques;
+        let limite = Utc::now() + EXPIRATION_PROCHE;
+        let mut marques: Vec<(DateTime<Utc>, String)> = bot.database.values()
+            .flat_map(|ecrit| ecrit.interesses.iter().filter_map(move |interet| marques_config.delais(&interet.type_)
+                .map(|delais| *interet.debut_delais() + delais.expiration)
+                .filter(|expiration| *expiration <= limite)
+                .map(|expiration| (expiration, format!("{} : marque de {} ({}), retirée le {}",
+                                                       ecrit.lien_markdown(), interet.name, interet.type_,
+                                                       expiration.format("%d/%m/%Y"))))))
+            .collect();
+        marques.sort_by_key(|(expiration, _)| *expiration);
+
+        CreateEmbed::new()
+            .author(CreateEmbedAuthor::new("Bilan de l’activité"))
+            .title(format!("Du {} au {}", debut.format("%d/%m/%Y"), fin.format("%d/%m/%Y")))
+            .fields(vec![
+                (format!("Nouveaux écrits ({})", nouveaux.len()), section(nouveaux), false),
+                (format!("Écrits critiqués ({})", critiques.len()), section(critiques), false),
+                (format!("Écrits validés ou publiés ({})", valides.len()), section(valides), false),
+                ("Écrits ouverts les plus anciens".to_string(), section(anciens), false),
+                (format!("Marques retirées d’ici une semaine ({})", marques.len()),
+                 section(marques.into_iter().map(|(_, ligne)| ligne).collect()), false),
+            ])
+            .color(73887)
+            .timestamp(Timestamp::now())
+    }
+
+    /// Publie chaque semaine, au jour et à l’heure configurés, le bilan de la semaine écoulée.
+    /// Lancée une seule fois, à la connexion du bot.
+    pub async fn publier_bilans(ctx: SerenityContext, bot: DataType<Self>) {
+        let Some(config) = &config::get().bilan else {
+            return;
+        };
+        loop {
+            let publication = prochaine_publication(config, Local::now());
+            tokio::time::sleep((publication - Local::now()).to_std().unwrap_or_default()).await;
+            let fin = Timestamp::now();
+            let debut = Timestamp::from(*fin - TimeDelta::weeks(1));
+            let bilan = Self::bilan(&*bot.lock().await, debut, fin);
+            if let Err(e) = config.salon.send_message(&ctx, CreateMessage::new().embed(bilan)).await {
+                eprintln!("Erreur lors de la publication du bilan hebdomadaire : {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use fondabots_lib::{tools, Bot, ErrType, Object};
+use poise::serenity_prelude as serenity;
+use serenity::all::{ButtonStyle, ComponentInteraction, Context as SerenityContext, CreateActionRow, CreateButton,
+                    CreateInteractionResponse, CreateInteractionResponseMessage};
+
+use super::{statistiques, Ecrit};
+
+/// Durée pendant laquelle une action faite par bouton peut être annulée par son auteur.
+const DELAI_ANNULATION: Duration = Duration::from_secs(5 * 60);
+
+/// État d’un écrit avant une action faite par bouton, conservé pour pouvoir l’annuler.
+struct Annulation {
+    jeton: u64,
+    membre: u64,
+    avant: Ecrit,
+    /// Taille de l’historique juste après l’action, pour détecter une modification ultérieure.
+    modifications: usize,
+    expiration: Instant,
+}
+
+static ANNULATIONS: Mutex<Vec<Annulation>> = Mutex::new(Vec::new());
+
+impl Ecrit {
+    /// Demande confirmation avant une action destructrice (« Critiqué » ou « Refusé »).
+    pub(super) async fn demander_confirmation(ctx: &SerenityContext, interaction: &ComponentInteraction, ecrit: &Ecrit, action: &str) -> Result<(), ErrType> {
+        let (question, confirmer) = match action {
+            "c" => (format!("Marquer « {} » comme critiqué ? Toutes ses marques d’intérêt seront supprimées.", ecrit.nom), "Critiqué"),
+            _ => (format!("Refuser « {} » ?", ecrit.nom), "Refusé"),
+        };
+        interaction.create_response(ctx, CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(question).ephemeral(true)
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(format!("ok-{}-{action}", ecrit.id)).label(confirmer).style(ButtonStyle::Danger),
+                    CreateButton::new(format!("no-{}", ecrit.id)).label("Ne rien faire").style(ButtonStyle::Secondary),
+                ])]))).await?;
+        Ok(())
+    }
+
+    /// Répond à une action confirmée en proposant pendant quelques minutes de revenir à `avant`,
+    /// l’état de l’écrit archivé juste avant l’action.
+    pub(super) async fn proposer_annulation(ctx: &SerenityContext, interaction: &ComponentInteraction, bot: &Bot<Self>, avant: Ecrit, resultat: String) -> Result<(), ErrType> {
+        let jeton = interaction.id.get();
+        {
+            let mut annulations = ANNULATIONS.lock().unwrap();
+            annulations.retain(|annulation| annulation.expiration > Instant::now());
+            annulations.push(Annulation {
+                jeton,
+                membre: interaction.user.id.get(),
+                modifications: bot.database.get(&avant.id).map_or(0, |ecrit| ecrit.historique.len()),
+                avant,
+                expiration: Instant::now() + DELAI_ANNULATION,
+            });
+        }
+        interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().content(format!("{resultat} Vous pouvez annuler pendant {} minutes.", DELAI_ANNULATION.as_secs() / 60))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(format!("an-{jeton}")).label("Annuler").style(ButtonStyle::Secondary)
+                ])]))).await?;
+        Ok(())
+    }
+
+    /// Journalise une action déjà effectuée : un échec du salon de journal ne doit pas interrompre
+    /// le traitement de l’action, qui serait alors ni enregistrée ni annulable.
+    pub(super) async fn journaliser(ctx: &SerenityContext, bot: &Bot<Self>, message: String) {
+        if let Err(e) = bot.log(ctx, message.clone()).await {
+            eprintln!("Impossible de journaliser « {message} » : {e}");
+        }
+    }
+
+    /// Bouton « Annuler » : remet l’écrit dans l’état archivé avant l’action.
+    pub(super) async fn bouton_annuler(ctx: &SerenityContext, interaction: &ComponentInteraction, bot: &mut Bot<Self>, jeton: u64) -> Result<(), ErrType> {
+        let annulation = {
+            let mut annulations = ANNULATIONS.lock().unwrap();
+            annulations.iter().position(|annulation| annulation.jeton == jeton)
+                .map(|index| annulations.remove(index))
+        };
+        let refus = match &annulation {
+            None => Some("Cette action ne peut plus être annulée."),
+            Some(annulation) if annulation.expiration <= Instant::now() => Some("Le délai d’annulation est dépassé."),
+            Some(annulation) if annulation.membre != interaction.user.id.get() => Some("Seule la personne ayant fait l’action peut l’annuler."),
+            Some(annulation) if bot.database.get(&annulation.avant.id)
+                .is_none_or(|ecrit| ecrit.historique.len() != annulation.modifications) =>
+                Some("L’écrit a été modifié depuis : utilisez /annuler ou corrigez-le à la main."),
+            Some(_) => None
+        };
+        if let Some(refus) = refus {
+            /* Une annulation refusée à un autre membre reste disponible pour son auteur. */
+            if let Some(annulation) = annulation.filter(|annulation| annulation.membre != interaction.user.id.get()) {
+                ANNULATIONS.lock().unwrap().push(annulation);
+            }
+            interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(refus).components(vec![]))).await?;
+            return Ok(());
+        }
+
+        let mut avant = annulation.unwrap().avant;
+        let id = avant.id;
+        avant.set_modified(true);
+        bot.database.insert(id, avant);
+        statistiques::retirer_annules(bot.database.get(&id));
+        interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().content("Action annulée.").components(vec![]))).await?;
+        bot.log(&ctx, format!("{} a annulé sa dernière action sur {} (id: {id}).",
+                              tools::user_desc(&interaction.user),
+                              bot.database.get(&id).unwrap().get_name()
+        )).await?;
+        bot.update_affichans(ctx).await?;
+        Self::sauvegarder(bot)?;
+        Ok(())
+    }
+}
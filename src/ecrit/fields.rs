@@ -1,8 +1,11 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+use chrono::{DateTime, Utc};
+use fondabots_lib::command_data::Permission;
 use fondabots_lib::object::Field;
 use fondabots_lib::tools::basicize;
+use fondabots_lib::yaml_rust2::Yaml;
 use fondabots_lib::ErrType;
 use poise::{serenity_prelude as serenity, ChoiceParameter};
 use serenity::all::{ButtonStyle, CreateActionRow, CreateButton, Timestamp};
@@ -10,6 +13,7 @@ use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use super::Ecrit;
+use crate::config;
 
 #[derive(EnumIter, Clone, PartialEq, Eq, ChoiceParameter, Debug)]
 pub enum Status {
@@ -55,6 +59,40 @@ impl FromStr for Status {
     }
 }
 
+impl Status {
+    /// Vérifie qu’un écrit du type donné peut passer de ce statut au statut `vers`. Renvoie la
+    /// permission nécessaire pour effectuer la transition, ou la raison pour laquelle elle est interdite.
+    pub fn transition(&self, vers: &Status, type_: &Type) -> Result<Permission, String> {
+        use Status::*;
+        if self == vers {
+            return Err(format!("L’écrit est déjà au statut {vers}."));
+        }
+        match (vers, type_) {
+            (Valide | Publie, Type::Idee) =>
+                return Err("Une idée ne peut être ni validée ni publiée : une fois validée, elle devient un rapport en attente (`/valider`).".to_string()),
+            (Refuse, Type::Conte | Type::FormatGdi | Type::Autre) =>
+                return Err(format!("Seuls les rapports et les idées peuvent être refusés, pas un écrit de type {type_}.")),
+            _ => ()
+        }
+        let (depuis, permission): (&[Status], Permission) = match vers {
+            Ouvert => (&[OuvertPlus, EnAttente, EnPause, SansNouvelles, Inconnu, Infraction, Abandonne], Permission::WRITE),
+            OuvertPlus => (&[Ouvert], Permission::WRITE),
+            EnAttente | EnPause | SansNouvelles => (&[Ouvert, OuvertPlus, EnAttente, EnPause, SansNouvelles, Inconnu], Permission::WRITE),
+            Abandonne => (&[Ouvert, OuvertPlus, EnAttente, EnPause, SansNouvelles, Inconnu, Infraction], Permission::WRITE),
+            Publie => (&[Valide], Permission::WRITE),
+            Valide => (&[Ouvert, OuvertPlus, EnAttente, EnPause, SansNouvelles, Inconnu], Permission::MANAGE),
+            Refuse => (&[Ouvert, OuvertPlus, EnAttente, EnPause, SansNouvelles, Inconnu, Infraction], Permission::MANAGE),
+            Infraction => (&[Ouvert, OuvertPlus, EnAttente, EnPause, SansNouvelles, Inconnu], Permission::MANAGE),
+            Inconnu => (&[Ouvert, OuvertPlus, EnAttente, EnPause, SansNouvelles, Abandonne, Publie, Valide, Refuse, Infraction], Permission::MANAGE),
+        };
+        if depuis.contains(self) {
+            Ok(permission)
+        } else {
+            Err(format!("Un écrit au statut {self} ne peut pas passer au statut {vers}."))
+        }
+    }
+}
+
 impl Field<Ecrit> for Status {
     fn comply_with(obj: &Ecrit, field: &Option<Self>) -> bool {
         if let Some(field) = field {
@@ -138,34 +176,149 @@ impl Type {
 }
 
 
+/// Type d’une marque d’intérêt. La clé est enregistrée dans la base de données et dans
+/// l’identifiant des boutons, le nom est affiché.
+#[derive(EnumIter, Clone, PartialEq, Eq, ChoiceParameter, Debug)]
+pub enum InteretType {
+    #[name = "⊙ Exclusif"]
+    Exclusif,
+    #[name = "⊟ Immédiat"]
+    Immediat,
+    #[name = "⋄ Ouvert"]
+    Ouvert,
+    #[name = "∙ Intérêt simple"]
+    Simple,
+    #[name = "⋇ Collab recherchée"]
+    Collab
+}
+
+impl InteretType {
+    pub fn cle(&self) -> &'static str {
+        match self {
+            InteretType::Exclusif => "seul",
+            InteretType::Immediat => "instant",
+            InteretType::Ouvert => "ouvert",
+            InteretType::Simple => "longterme",
+            InteretType::Collab => "collab"
+        }
+    }
+
+    pub fn from_cle(cle: &str) -> Option<Self> {
+        InteretType::iter().find(|type_| type_.cle() == cle)
+    }
+}
+
+impl Display for InteretType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for InteretType {
+    type Err = ErrType;
+
+    /// Accepte la clé ou le nom affiché, ce dernier étant ce qu’enregistraient les anciennes
+    /// versions du bot. Les marques enregistrées comme « Inconnu? » deviennent des intérêts simples.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "Inconnu?" {
+            return Ok(InteretType::Simple);
+        }
+        let basic_s = basicize(s);
+        InteretType::from_cle(s)
+            .or_else(|| InteretType::iter().find(|v| basicize(v.to_string().as_str()) == basic_s))
+            .ok_or(ErrType::ObjectNotFound(format!("Type de marque {s} inexistant.")))
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Interet {
     pub name: String,
     pub date: Timestamp,
-    pub type_: String,
-    pub member: u64
+    pub type_: InteretType,
+    pub member: u64,
+    /// Date à laquelle il a été rappelé à l’auteur de la marque qu’elle allait être retirée.
+    pub rappel: Option<Timestamp>,
+    /// Date à laquelle l’auteur a conservé sa marque après un rappel. Elle remplace `date` comme
+    /// point de départ des délais de rappel et de retrait, sans changer la date de la marque.
+    pub prolongee: Option<Timestamp>
 }
 
 impl Interet {
+    pub fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        let erreur = |champ: &str| ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ} d’une marque d’intérêt."));
+        Ok(Self {
+            name: data["name"].as_str().ok_or(erreur("name"))?.to_string(),
+            date: Timestamp::from_unix_timestamp(data["date"].as_i64().ok_or(erreur("date"))?)?,
+            type_: InteretType::from_str(data["type"].as_str().ok_or(erreur("type"))?)?,
+            member: data["member"].as_i64().ok_or(erreur("member"))?.try_into()?,
+            rappel: data["rappel"].as_i64().and_then(|date| Timestamp::from_unix_timestamp(date).ok()),
+            prolongee: data["prolongee"].as_i64().and_then(|date| Timestamp::from_unix_timestamp(date).ok()),
+        })
+    }
+
     pub fn action_row(ecrit_id: u64) -> CreateActionRow {
-        CreateActionRow::Buttons(vec![
-            CreateButton::new(format!("tm-{ecrit_id}-seul")).label("⊙ Exclusif").style(ButtonStyle::Secondary),
-            CreateButton::new(format!("tm-{ecrit_id}-instant")).label("⊟ Immédiat").style(ButtonStyle::Secondary),
-            CreateButton::new(format!("tm-{ecrit_id}-ouvert")).label("⋄ Ouvert").style(ButtonStyle::Secondary),
-            CreateButton::new(format!("tm-{ecrit_id}-longterme")).label("∙ Intérêt simple").style(ButtonStyle::Secondary),
-            CreateButton::new(format!("tm-{ecrit_id}-collab")).label("⋇ Collab recherchée").style(ButtonStyle::Secondary),
-
-        ])
-    }
-
-    pub fn get_type(type_str: &str) -> &str {
-        match type_str {
-            "seul" => "⊙ Exclusif",
-            "instant" => "⊟ Immédiat",
-            "ouvert" => "⋄ Ouvert",
-            "longterme" => "∙ Intérêt simple",
-            "collab" => "⋇ Collab recherchée",
-            _ => "Inconnu?"
+        CreateActionRow::Buttons(InteretType::iter().map(|type_|
+            CreateButton::new(format!("tm-{ecrit_id}-{}", type_.cle())).label(type_.to_string()).style(ButtonStyle::Secondary)
+        ).collect())
+    }
+
+    /// Fin de la réservation de l’écrit si la marque est exclusive et toujours en cours.
+    pub fn fin_reservation(&self) -> Option<DateTime<Utc>> {
+        let fin = *self.date + config::get().marques.reservation_exclusive;
+        (self.type_ == InteretType::Exclusif && fin > Utc::now()).then_some(fin)
+    }
+
+    /// Une marque immédiate expire après la durée configurée.
+    pub fn expiree(&self) -> bool {
+        self.type_ == InteretType::Immediat && *self.date + config::get().marques.expiration_immediate <= Utc::now()
+    }
+
+    /// Point de départ des délais de rappel et de retrait de la marque.
+    pub fn debut_delais(&self) -> Timestamp {
+        self.prolongee.unwrap_or(self.date)
+    }
+
+    /// La marque a été posée par ce membre, ou pour ce nom par procuration.
+    pub fn appartient_a(&self, membre: u64, nom: &str) -> bool {
+        if self.member == 0 {
+            self.name == nom
+        } else {
+            self.member == membre
         }
     }
-}
\ No newline at end of file
+
+    /// Description de la marque utilisée dans l’historique de l’écrit.
+    pub fn description(&self) -> String {
+        format!("{} par {}", self.type_, self.name)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idee_ni_validee_ni_publiee() {
+        assert!(Status::Valide.transition(&Status::Publie, &Type::Idee).is_err());
+        assert!(Status::Ouvert.transition(&Status::Valide, &Type::Idee).is_err());
+        assert!(Status::Valide.transition(&Status::Publie, &Type::Rapport).is_ok());
+    }
+
+    #[test]
+    fn refuse_ne_peut_pas_etre_rouvert() {
+        assert!(Status::Refuse.transition(&Status::Ouvert, &Type::Rapport).is_err());
+    }
+
+    #[test]
+    fn meme_statut_refuse() {
+        assert!(Status::Ouvert.transition(&Status::Ouvert, &Type::Conte).is_err());
+        assert!(Status::Refuse.transition(&Status::Refuse, &Type::Rapport).is_err());
+    }
+
+    #[test]
+    fn permissions() {
+        assert!(matches!(Status::Ouvert.transition(&Status::Refuse, &Type::Rapport), Ok(Permission::MANAGE)));
+        assert!(matches!(Status::Ouvert.transition(&Status::Valide, &Type::Conte), Ok(Permission::MANAGE)));
+        assert!(matches!(Status::Ouvert.transition(&Status::EnPause, &Type::Conte), Ok(Permission::WRITE)));
+        assert!(Status::Ouvert.transition(&Status::Refuse, &Type::Conte).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use std::cmp::max;
+use std::collections::HashMap;
+use std::mem::take;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use fondabots_lib::tools::basicize;
+use fondabots_lib::{Bot, DataType, ErrType};
+use rss::{Channel, Item};
+use poise::futures_util::{stream, StreamExt};
+use poise::serenity_prelude as serenity;
+use serenity::all::Timestamp;
+
+use crate::config::{self, Flux};
+use super::auteurs;
+use super::fields::Status;
+use super::historique::{Acteur, Action};
+use super::titre::AnalyseurTitre;
+use super::Ecrit;
+
+/// Date de l’entrée la plus récente lue dans chaque flux, par nom de flux. Un flux absent reprend à
+/// `last_rss_update`, qui n’avance que jusqu’au flux le plus en retard : un flux inaccessible ne
+/// perd ainsi pas les entrées publiées pendant que les autres avançaient.
+static FILIGRANES: Mutex<Option<HashMap<String, DateTime<Utc>>>> = Mutex::new(None);
+
+/// Nombre maximal de fils lus à chaque mise à jour : les fils sont lus à tour de rôle, du plus
+/// anciennement lu au plus récemment lu.
+const FILS_PAR_LECTURE: usize = 40;
+
+/// Nombre de fils lus en même temps.
+const LECTURES_SIMULTANEES: usize = 4;
+
+/// Ancienneté au-delà de laquelle le fil d’un écrit sans nouvelles n’est plus suivi.
+const SUIVI_SANS_NOUVELLES: TimeDelta = TimeDelta::days(180);
+
+/// Moment de la dernière lecture du fil de chaque écrit suivi.
+static LECTURES_FILS: Mutex<Option<HashMap<u64, Instant>>> = Mutex::new(None);
+
+/// Origine d’un flux RSS : le site lui-même, un serveur le remplaçant ou un fichier local.
+#[derive(Clone, Debug)]
+pub enum SourceFlux {
+    Http(String),
+    Fichier(PathBuf),
+}
+
+impl SourceFlux {
+    /// Détermine la source d’un flux. Les URL en `file://` désignent un fichier local ; sinon, si
+    /// `url_base` est donnée, elle remplace le protocole et l’hôte de l’URL.
+    pub fn new(url: &str, url_base: Option<&str>) -> Self {
+        if let Some(chemin) = url.strip_prefix("file://") {
+            return SourceFlux::Fichier(PathBuf::from(chemin));
+        }
+        match url_base {
+            Some(url_base) => {
+                let chemin = url.split_once("://")
+                    .map(|(_, reste)| reste.find('/').map_or("", |debut| &reste[debut..]))
+                    .unwrap_or(url);
+                SourceFlux::Http(format!("{}{chemin}", url_base.trim_end_matches('/')))
+            },
+            None => SourceFlux::Http(url.to_string())
+        }
+    }
+
+    pub async fn lire(&self) -> Result<Channel, ErrType> {
+        Ok(match self {
+            SourceFlux::Http(url) => Channel::read_from(&reqwest::get(url).await?.bytes().await?[..])?,
+            SourceFlux::Fichier(chemin) => Channel::read_from(&std::fs::read(chemin).map_err(|e|
+                ErrType::ObjectNotFound(format!("Impossible de lire le flux {} : {e}", chemin.display()))
+            )?[..])?
+        })
+    }
+}
+
+impl Ecrit {
+    /// Ajoute à la base de données les écrits du flux publiés après `depuis` qui n’y sont pas encore.
+    /// Renvoie la date de l’entrée la plus récente lue et le nombre d’écrits ajoutés.
+    pub fn ingerer(bot: &mut Bot<Self>, flux: &Flux, rss: Channel, depuis: DateTime<Utc>, analyseur: &AnalyseurTitre) -> (Option<DateTime<Utc>>, usize) {
+        let mut ajouts = 0;
+        let last_date = rss.items.into_iter()
+            .filter_map(|entry| match DateTime::parse_from_rfc2822(entry.pub_date.as_deref().unwrap_or_default()) {
+                Ok(date) => if date.to_utc() > depuis {
+                    Some((date.to_utc(), entry))
+                } else {None},
+                Err(_) => {
+                    eprintln!("Erreur lors de la récupération des flux RSS : pas de date.");
+                    None
+                }
+            })
+            .filter(|(_, entry)| entry.title.as_ref().is_some_and(|str| { str.contains("]") }))
+            .filter_map(|(date, mut entry)| {
+                let titre = entry.title.as_ref().and_then(|entry_title| analyseur.analyser(entry_title.as_str()));
+
+                let lien = take(&mut entry.link);
+
+                let auteur = auteur_entree(&entry);
+
+                if titre.is_none() || lien.is_none() || auteur.is_none() {
+                    eprintln!("L'une des données d'une entrée RSS (titre, line ou auteur) est incorrecte.");
+                    return None;
+                }
+                let (titre, lien, auteur) = (titre.unwrap(), lien.unwrap(), auteur.unwrap().to_string());
+                let tags = titre.tags();
+
+                let id = Ecrit::find_id(&lien);
+                if id.is_none() {
+                    eprintln!("Lien malformé dans une entrée RSS : impossible de récupérer l'ID.");
+                    return None;
+                }
+                let id = id.unwrap();
+
+                Some((date, Ecrit {
+                    status: flux.statut.clone(),
+                    type_: titre.type_.unwrap_or(flux.type_.clone()),
+                    nom: titre.nom,
+                    lien,
+                    last_update: Timestamp::now(),
+                    interesses: vec![],
+                    modified: false,
+                    tags,
+                    id,
+                    discord_chan: None,
+                    flux: Some(flux.nom.clone()),
+                    historique: vec![],
+                    membre_auteur: auteurs::membre(&auteur),
+                    auteur
+                }))
+            }).map(|(date, mut ecrit)| {
+            if bot.database.contains_key(&ecrit.id) {
+                eprintln!("Ajout RSS d’un écrit déjà ajouté. Informations : écrit [{}] - last_rss_update [{}] - date>last_rss_update [{}]", date, bot.last_rss_update, date > bot.last_rss_update);
+            } else {
+                let source = ecrit.flux.as_ref().map(|flux| format!("Flux {flux}"));
+                ecrit.historiser(&Acteur::Critibot, Action::Ajout, None, source);
+                bot.database.insert(ecrit.id, ecrit);
+                ajouts += 1;
+            }
+            date
+        }).max();
+        (last_date, ajouts)
+    }
+
+    /// Ajoute à la base de données les nouveaux écrits de tous les flux configurés.
+    pub async fn maj_flux(bot: &DataType<Self>) -> Result<(), ErrType> {
+        let config = config::get();
+        let analyseur = AnalyseurTitre::new(config.balises.clone())?;
+        let bot = &mut bot.lock().await;
+        let bot_last_rss_update = bot.last_rss_update;
+        let mut filigranes = FILIGRANES.lock().unwrap().clone().unwrap_or_default();
+        let mut ajouts = 0;
+
+        for flux in &config.flux {
+            let depuis = filigranes.get(&flux.nom).copied().unwrap_or(bot_last_rss_update);
+            /* Un flux inaccessible ne doit pas empêcher la lecture des autres ; il reprendra à sa
+             * propre date à la prochaine lecture. */
+            let rss = match SourceFlux::new(&flux.url, config.url_base.as_deref()).lire().await {
+                Ok(rss) => rss,
+                Err(e) => {
+                    eprintln!("Erreur lors de la récupération du flux RSS {} : {e}", flux.nom);
+                    continue;
+                }
+            };
+            let (last_date_flux, ajouts_flux) = Self::ingerer(bot, flux, rss, depuis, &analyseur);
+            filigranes.insert(flux.nom.clone(), max(depuis, last_date_flux.unwrap_or(depuis)));
+            ajouts += ajouts_flux;
+        }
+
+        /* La date sauvegardée est celle du flux le plus en retard, pour qu’un redémarrage ne saute
+         * aucune entrée. */
+        if let Some(plus_en_retard) = config.flux.iter()
+            .map(|flux| filigranes.get(&flux.nom).copied().unwrap_or(bot_last_rss_update))
+            .min() {
+            bot.last_rss_update = max(plus_en_retard, bot.last_rss_update);
+        }
+        *FILIGRANES.lock().unwrap() = Some(filigranes);
+        if ajouts > 0 {
+            bot.update_affichans = true;
+        }
+        Ok(())
+    }
+
+    /// URL du flux des messages du fil de l’écrit sur le forum.
+    pub fn url_flux_fil(&self) -> String {
+        let origine = self.lien.split_once("://")
+            .map(|(protocole, reste)| format!("{protocole}://{}", reste.split('/').next().unwrap_or(reste)))
+            .unwrap_or_default();
+        format!("{origine}/feed/forum/t-{}.xml", self.id)
+    }
+
+    /// Date du dernier message de l’auteur dans le flux du fil de l’écrit, s’il est postérieur à la
+    /// dernière mise à jour de l’écrit.
+    pub fn dernier_message_auteur(&self, rss: &Channel) -> Option<DateTime<Utc>> {
+        let auteur = basicize(self.auteur.as_str());
+        rss.items.iter()
+            .filter(|entry| auteur_entree(entry).is_some_and(|nom| basicize(nom) == auteur))
+            .filter_map(|entry| entry.pub_date.as_deref().and_then(|date| DateTime::parse_from_rfc2822(date).ok()))
+            .map(|date| date.to_utc())
+            .filter(|date| *date > *self.last_update)
+            .max()
+    }
+
+    /// Rouvre un écrit suite à un nouveau message de son auteur.
+    pub fn relancer(&mut self, date: DateTime<Utc>) {
+        self.up_par(&Acteur::Critibot);
+        self.last_update = Timestamp::from(date);
+    }
+
+    /// Le fil de l’écrit est suivi pour détecter un nouveau message de l’auteur : écrits en attente,
+    /// et écrits sans nouvelles depuis moins de six mois.
+    fn fil_suivi(&self) -> bool {
+        self.status == Status::EnAttente
+            || (self.status == Status::SansNouvelles && Utc::now() - *self.last_update < SUIVI_SANS_NOUVELLES)
+    }
+
+    /// Relance les écrits en attente ou sans nouvelles dont l’auteur a posté dans leur fil depuis leur
+    /// dernière mise à jour. Seul un lot de fils est lu à chaque appel, à tour de rôle.
+    pub async fn suivre_fils(bot: &DataType<Self>) -> Result<(), ErrType> {
+        let url_base = config::get().url_base.as_deref();
+        let fils_suivis: Vec<(u64, String)> = {
+            let bot = bot.lock().await;
+            let mut lectures = LECTURES_FILS.lock().unwrap();
+            let lectures = lectures.get_or_insert_with(HashMap::new);
+            lectures.retain(|id, _| bot.database.get(id).is_some_and(Ecrit::fil_suivi));
+            let mut fils: Vec<&Ecrit> = bot.database.values().filter(|ecrit| ecrit.fil_suivi()).collect();
+            /* Les fils jamais lus passent en premier. */
+            fils.sort_by_key(|ecrit| lectures.get(&ecrit.id).copied());
+            let fils: Vec<(u64, String)> = fils.into_iter().take(FILS_PAR_LECTURE)
+                .map(|ecrit| (ecrit.id, ecrit.url_flux_fil()))
+                .collect();
+            let maintenant = Instant::now();
+            for (id, _) in &fils {
+                lectures.insert(*id, maintenant);
+            }
+            fils
+        };
+
+        /* Les flux sont lus sans garder le verrou sur le bot, quelques-uns à la fois. */
+        let flux_fils: Vec<(u64, Channel)> = stream::iter(fils_suivis)
+            .map(|(id, url)| async move { (id, SourceFlux::new(&url, url_base).lire().await) })
+            .buffer_unordered(LECTURES_SIMULTANEES)
+            .filter_map(|(id, rss)| async move {
+                rss.map_err(|e| eprintln!("Erreur lors de la récupération du flux du fil de l’écrit {id} : {e}")).ok()
+                    .map(|rss| (id, rss))
+            })
+            .collect().await;
+
+        let bot = &mut bot.lock().await;
+        let mut relances = Vec::new();
+        for (id, rss) in flux_fils {
+            /* Le statut a pu changer pendant la lecture des flux. */
+            if let Some(ecrit) = bot.database.get_mut(&id).filter(|ecrit| ecrit.fil_suivi()) {
+                if let Some(date) = ecrit.dernier_message_auteur(&rss) {
+                    let ancien_statut = ecrit.status.clone();
+                    ecrit.relancer(date);
+                    relances.push(format!("{} (id: {id}) relancé automatiquement : nouveau message de {} dans le fil le {} (statut précédent : {ancien_statut}, nouveau statut : {}).",
+                                          ecrit.nom, ecrit.auteur, date.format("%d/%m/%Y à %H:%M"), ecrit.status));
+                }
+            }
+        }
+
+        if !relances.is_empty() {
+            bot.update_affichans = true;
+        }
+        for relance in relances {
+            match crate::contexte() {
+                Some(ctx) => bot.log(ctx, relance).await?,
+                None => eprintln!("{relance}")
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Nom wikidot de l’auteur d’une entrée d’un flux du forum.
+fn auteur_entree(entry: &Item) -> Option<&str> {
+    entry.extensions().get("wikidot")
+        .and_then(|wikidot| wikidot.get("authorName")
+            .and_then(|author_name| author_name.first()
+                .and_then(|author_name| author_name.value())
+            )
+        )
+}
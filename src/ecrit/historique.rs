@@ -0,0 +1,160 @@
+use std::fmt::{Display, Formatter};
+
+use fondabots_lib::yaml_rust2::{yaml, Yaml};
+use fondabots_lib::ErrType;
+use poise::serenity_prelude as serenity;
+use serenity::all::{Timestamp, User};
+
+/// Auteur d’une modification d’un écrit.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Acteur {
+    Membre { id: u64, nom: String },
+    /// Modification automatique (flux RSS, tâches planifiées…).
+    Critibot,
+}
+
+impl From<&User> for Acteur {
+    fn from(user: &User) -> Self {
+        Acteur::Membre {
+            id: user.id.get(),
+            nom: user.display_name().to_string(),
+        }
+    }
+}
+
+impl Display for Acteur {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Acteur::Membre { id, nom } => write!(f, "{nom} (<@{id}>)"),
+            Acteur::Critibot => write!(f, "Critibot")
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    Statut,
+    Type,
+    Auteur,
+    AjoutTag,
+    RetraitTag,
+    Marque,
+    RetraitMarque,
+    LienDiscord,
+    /// Ajout de l’écrit à la base de données.
+    Ajout,
+    /// Avertissement de l’auteur avant l’archivage automatique.
+    AvertissementArchivage,
+    /// Critique de l’écrit, qui retire ses marques d’intérêt.
+    Critique,
+    /// Marque conservée par son auteur après un rappel, ce qui repousse son retrait.
+    ProlongationMarque,
+}
+
+impl Action {
+    const CLES: [(Action, &'static str); 12] = [
+        (Action::Statut, "statut"),
+        (Action::Type, "type"),
+        (Action::Auteur, "auteur"),
+        (Action::AjoutTag, "ajout-tag"),
+        (Action::RetraitTag, "retrait-tag"),
+        (Action::Marque, "marque"),
+        (Action::RetraitMarque, "retrait-marque"),
+        (Action::LienDiscord, "lien-discord"),
+        (Action::Ajout, "ajout"),
+        (Action::AvertissementArchivage, "avertissement-archivage"),
+        (Action::Critique, "critique"),
+        (Action::ProlongationMarque, "prolongation-marque"),
+    ];
+
+    /// Clé stable utilisée dans la base de données.
+    pub fn cle(&self) -> &'static str {
+        Self::CLES.iter().find(|(action, _)| action == self).map(|(_, cle)| *cle).unwrap()
+    }
+
+    pub fn from_cle(cle: &str) -> Option<Self> {
+        Self::CLES.iter().find(|(_, cle_action)| *cle_action == cle).map(|(action, _)| action.clone())
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Action::Statut => "Statut",
+            Action::Type => "Type",
+            Action::Auteur => "Auteur",
+            Action::AjoutTag => "Ajout de tag",
+            Action::RetraitTag => "Retrait de tag",
+            Action::Marque => "Marque d’intérêt",
+            Action::RetraitMarque => "Retrait de marque",
+            Action::LienDiscord => "Lien Discord",
+            Action::Ajout => "Ajout",
+            Action::AvertissementArchivage => "Avertissement d’archivage",
+            Action::Critique => "Critique",
+            Action::ProlongationMarque => "Prolongation de marque"
+        })
+    }
+}
+
+/// Modification d’un écrit conservée dans son historique.
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntreeHistorique {
+    pub date: Timestamp,
+    pub acteur: Acteur,
+    pub action: Action,
+    pub ancien: Option<String>,
+    pub nouveau: Option<String>,
+}
+
+impl EntreeHistorique {
+    pub fn get_list_entry(&self) -> String {
+        let changement = match (&self.ancien, &self.nouveau) {
+            (Some(ancien), Some(nouveau)) => format!("{ancien} → {nouveau}"),
+            (Some(ancien), None) => format!("~~{ancien}~~"),
+            (None, Some(nouveau)) => nouveau.clone(),
+            (None, None) => String::new()
+        };
+        format!("**{}** — {}\n{} : {changement}\n\n", self.date.format("%d/%m/%Y %H:%M"), self.acteur, self.action)
+    }
+
+    pub fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        let erreur = |champ: &str| ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ} de l’historique."));
+        let acteur_id = data["acteur-id"].as_i64().ok_or(erreur("acteur-id"))?;
+        Ok(Self {
+            date: Timestamp::from_unix_timestamp(data["date"].as_i64().ok_or(erreur("date"))?)?,
+            acteur: if acteur_id == 0 {
+                Acteur::Critibot
+            } else {
+                Acteur::Membre {
+                    id: acteur_id.unsigned_abs(),
+                    nom: data["acteur"].as_str().ok_or(erreur("acteur"))?.to_string(),
+                }
+            },
+            action: Action::from_cle(data["action"].as_str().ok_or(erreur("action"))?).ok_or(erreur("action"))?,
+            ancien: data["ancien"].as_str().map(|ancien| ancien.to_string()),
+            nouveau: data["nouveau"].as_str().map(|nouveau| nouveau.to_string()),
+        })
+    }
+
+    pub fn serialize(&self) -> Yaml {
+        let mut hash = yaml::Hash::new();
+        hash.insert(Yaml::String("date".to_string()), Yaml::Integer(self.date.timestamp()));
+        match &self.acteur {
+            Acteur::Membre { id, nom } => {
+                hash.insert(Yaml::String("acteur-id".to_string()), Yaml::Integer(*id as i64));
+                hash.insert(Yaml::String("acteur".to_string()), Yaml::String(nom.clone()));
+            }
+            Acteur::Critibot => {
+                hash.insert(Yaml::String("acteur-id".to_string()), Yaml::Integer(0));
+            }
+        }
+        hash.insert(Yaml::String("action".to_string()), Yaml::String(self.action.cle().to_string()));
+        if let Some(ancien) = &self.ancien {
+            hash.insert(Yaml::String("ancien".to_string()), Yaml::String(ancien.clone()));
+        }
+        if let Some(nouveau) = &self.nouveau {
+            hash.insert(Yaml::String("nouveau".to_string()), Yaml::String(nouveau.clone()));
+        }
+        Yaml::Hash(hash)
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::Utc;
+use fondabots_lib::{Bot, DataType, ErrType};
+use poise::serenity_prelude as serenity;
+use serenity::all::{ButtonStyle, ChannelId, ComponentInteraction, Context as SerenityContext, CreateActionRow, CreateButton,
+                    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, Timestamp, UserId};
+
+use crate::config;
+use super::historique::{Acteur, Action};
+use super::statistiques::{self, Evenement};
+use super::Ecrit;
+
+/// Rappel à envoyer à l’auteur d’une marque avant son retrait.
+struct Rappel {
+    membre: u64,
+    ecrit_id: u64,
+    fil: Option<ChannelId>,
+    message: String,
+}
+
+impl Ecrit {
+    /// Retire les marques expirées de la base de données et renvoie les rappels à envoyer pour les
+    /// marques qui expireront bientôt. Les événements de statistiques des retraits sont ajoutés à `evenements`.
+    fn verifier_marques(&mut self, retraits: &mut Vec<String>, evenements: &mut Vec<Evenement>) -> Vec<Rappel> {
+        let marques = &config::get().marques;
+        let maintenant = Utc::now();
+        let nom = self.nom.clone();
+        let id = self.id;
+
+        while let Some(interet) = self.interesses.iter()
+            .find(|interet| interet.expiree() || marques.delais(&interet.type_)
+                .is_some_and(|delais| *interet.debut_delais() + delais.expiration <= maintenant))
+            .cloned() {
+            /* Les marques par procuration n’ont pas d’identifiant de membre. */
+            let liberation = if interet.member != 0 {
+                self.liberer_id(interet.member, &Acteur::Critibot)
+            } else {
+                self.liberer_name(&interet.name, &Acteur::Critibot)
+            };
+            let Some(evenement) = liberation else {
+                break;
+            };
+            evenements.push(evenement);
+            retraits.push(format!("Marque « {} » retirée automatiquement de l’écrit {nom} (id: {id}) faute d’activité.",
+                                  interet.description()));
+        }
+
+        let mut rappels = Vec::new();
+        for interet in self.interesses.iter_mut().filter(|interet| interet.member != 0 && interet.rappel.is_none()) {
+            if let Some(delais) = marques.delais(&interet.type_).filter(|delais| *interet.debut_delais() + delais.rappel <= maintenant) {
+                interet.rappel = Some(Timestamp::now());
+                rappels.push(Rappel {
+                    membre: interet.member,
+                    ecrit_id: id,
+                    fil: self.discord_chan,
+                    message: format!("Votre marque « {} » sur l’écrit « {nom} » ({}) date du {}. Sans réaction de votre part, \
+                                      elle sera retirée le {}.", interet.type_, self.lien, interet.date.format("%d/%m/%Y"),
+                                     (*interet.debut_delais() + delais.expiration).format("%d/%m/%Y")),
+                });
+            }
+        }
+        if !rappels.is_empty() {
+            self.modified = true;
+        }
+        rappels
+    }
+
+    /// Envoie les rappels et retire les marques expirées de toute la base de données.
+    pub async fn entretenir_marques(ctx: &SerenityContext, bot: &DataType<Self>) -> Result<(), ErrType> {
+        let rappels = {
+            let bot = &mut bot.lock().await;
+            let mut retraits = Vec::new();
+            let mut evenements = Vec::new();
+            let rappels: Vec<Rappel> = bot.database.values_mut()
+                .flat_map(|ecrit| ecrit.verifier_marques(&mut retraits, &mut evenements))
+                .collect();
+            for retrait in &retraits {
+                bot.log(ctx, retrait.clone()).await?;
+            }
+            if !retraits.is_empty() {
+                bot.update_affichans(ctx).await?;
+            }
+            if !retraits.is_empty() || !rappels.is_empty() {
+                Self::sauvegarder(bot)?;
+            }
+            statistiques::enregistrer(evenements);
+            rappels
+        };
+
+        /* Les messages sont envoyés sans garder le verrou sur le bot. */
+        for rappel in rappels {
+            let message = CreateMessage::new().content(rappel.message.clone())
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(format!("rm-{}", rappel.ecrit_id)).label("Conserver ma marque").style(ButtonStyle::Primary)
+                ])]);
+            if UserId::new(rappel.membre).direct_message(ctx, message.clone()).await.is_err() {
+                /* Messages privés fermés : le membre est mentionné dans le fil de l’écrit s’il en a un. */
+                let envoye = match rappel.fil {
+                    Some(fil) => fil.send_message(ctx, message.content(format!("<@{}> {}", rappel.membre, rappel.message))).await.is_ok(),
+                    None => false
+                };
+                if !envoye {
+                    eprintln!("Impossible d’envoyer le rappel de marque au membre {} pour l’écrit {}.", rappel.membre, rappel.ecrit_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Vérifie régulièrement les marques d’intérêt. Lancée une seule fois, à la connexion du bot.
+    pub async fn surveiller_marques(ctx: SerenityContext, bot: DataType<Self>) {
+        let intervalle = config::get().marques.intervalle_verification.to_std()
+            .expect("L’intervalle de vérification des marques est positif.");
+        let mut horloge = tokio::time::interval(intervalle);
+        loop {
+            horloge.tick().await;
+            if let Err(e) = Self::entretenir_marques(&ctx, &bot).await {
+                eprintln!("Erreur lors de la vérification des marques : {e}");
+            }
+        }
+    }
+
+    /// Fait repartir de maintenant les délais de rappel et de retrait de la marque d’un membre, sans
+    /// changer sa date ni la fin d’une réservation exclusive. Renvoie `false` s’il n’a pas de marque.
+    fn conserver_marque(&mut self, membre: u64, acteur: &Acteur) -> bool {
+        let Some(interet) = self.interesses.iter_mut().find(|interet| interet.member == membre) else {
+            return false;
+        };
+        interet.prolongee = Some(Timestamp::now());
+        interet.rappel = None;
+        let description = interet.description();
+        self.historiser(acteur, Action::ProlongationMarque, None, Some(description));
+        true
+    }
+
+    /// Bouton « Conserver ma marque » des rappels.
+    pub(super) async fn bouton_conserver_marque(ctx: &SerenityContext, interaction: &ComponentInteraction, bot: &mut Bot<Self>, id: u64) -> Result<(), ErrType> {
+        let conservee = bot.database.get(&id)
+            .is_some_and(|ecrit| ecrit.interesses.iter().any(|interet| interet.member == interaction.user.id.get()));
+        if conservee {
+            bot.archive(vec![id]);
+            bot.database.get_mut(&id).unwrap().conserver_marque(interaction.user.id.get(), &Acteur::from(&interaction.user));
+        }
+        interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(if conservee { "Marque conservée." } else { "Cette marque n’existe plus." })
+                .components(vec![]))).await?;
+        if conservee {
+            Self::sauvegarder(bot)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecrit::fields::{Interet, InteretType, Status, Type};
+
+    #[test]
+    fn conserver_marque_garde_sa_date() {
+        let mut ecrit = Ecrit::new("Le Mur".to_string(), "http://fondationscp.wikidot.com/forum/t-1/".to_string(),
+                                   Type::Rapport, Status::Ouvert, "Dr Foo".to_string()).unwrap();
+        let date = Timestamp::from_unix_timestamp(1_700_000_000).unwrap();
+        ecrit.interesses.push(Interet {
+            name: "Alice".to_string(),
+            date,
+            type_: InteretType::Exclusif,
+            member: 1,
+            rappel: Some(date),
+            prolongee: None,
+        });
+        assert!(!ecrit.conserver_marque(2, &Acteur::Critibot));
+        assert!(ecrit.conserver_marque(1, &Acteur::Critibot));
+
+        let interet = &ecrit.interesses[0];
+        assert_eq!((interet.date, interet.rappel), (date, None));
+        assert!(interet.debut_delais() > date);
+        assert_eq!(ecrit.historique.last().map(|entree| &entree.action), Some(&Action::ProlongationMarque));
+    }
+}
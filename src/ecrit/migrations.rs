@@ -0,0 +1,152 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+
+use fondabots_lib::yaml_rust2::{yaml, Yaml};
+use fondabots_lib::ErrType;
+
+use super::fields::InteretType;
+
+/// Version du format de la base de données écrit par [`super::Ecrit`]. Elle est enregistrée une seule
+/// fois pour toute la base, dans la clé `version` en tête du document ; une base sans cette clé est en
+/// version 1.
+pub const VERSION: i64 = 2;
+
+/// Une migration fait passer un écrit de la version `n` à la version `n + 1`.
+type Migration = fn(&mut yaml::Hash) -> Result<(), ErrType>;
+
+/// Migrations dans l’ordre : la première passe de la version 1 à la version 2, etc.
+const MIGRATIONS: [Migration; (VERSION - 1) as usize] = [
+    v1_vers_v2,
+];
+
+fn cle(nom: &str) -> Yaml {
+    Yaml::String(nom.to_string())
+}
+
+/// Version 2 : clés en kebab-case (`lastUpdate` devient `last-update`) et types des marques
+/// d’intérêt enregistrés par leur clé plutôt que par leur nom affiché.
+fn v1_vers_v2(ecrit: &mut yaml::Hash) -> Result<(), ErrType> {
+    if let Some(last_update) = ecrit.remove(&cle("lastUpdate")) {
+        ecrit.insert(cle("last-update"), last_update);
+    }
+    if let Some(Yaml::Array(interesses)) = ecrit.get_mut(&cle("interesses")) {
+        for interet in interesses.iter_mut() {
+            if let Yaml::Hash(interet) = interet {
+                if let Some(Yaml::String(type_)) = interet.get_mut(&cle("type")) {
+                    *type_ = InteretType::from_str(type_)?.cle().to_string();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Version du format d’un document de la base de données, lue dans sa clé `version`. Un document sans
+/// cette clé est en version 1.
+pub fn lire_version(document: &Yaml) -> Result<i64, ErrType> {
+    let version = match &document["version"] {
+        Yaml::BadValue => 1,
+        Yaml::Integer(version) if *version >= 1 => *version,
+        autre => return Err(ErrType::YamlParseError(format!("Version du format de la base de données invalide : {autre:?}")))
+    };
+    if version > VERSION {
+        return Err(ErrType::YamlParseError(format!(
+            "Base de données au format {version}, plus récent que celui de cette version du bot ({VERSION}).")));
+    }
+    Ok(version)
+}
+
+/// Ligne portant la version actuelle du format en tête de la base de données.
+fn ligne_version() -> String {
+    format!("version: {VERSION}")
+}
+
+/// Inscrit la version actuelle du format en tête de la base de données si elle n’y est pas. La
+/// bibliothèque réécrit la base sans cette clé à chaque enregistrement : elle doit être remise ensuite.
+pub fn inscrire_version(chemin_base: &str) -> Result<(), ErrType> {
+    let erreur_io = |e: std::io::Error| ErrType::YamlParseError(format!("Base de données {chemin_base} : {e}"));
+    /* Seules les premières lignes sont lues tant que la version est déjà inscrite. */
+    let entete: Vec<String> = match File::open(chemin_base) {
+        Ok(fichier) => BufReader::new(fichier).lines().take(2).collect::<Result<_, _>>().map_err(erreur_io)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(erreur_io(e))
+    };
+    if entete.iter().any(|ligne| *ligne == ligne_version()) {
+        return Ok(());
+    }
+    let contenu = fs::read_to_string(chemin_base).map_err(erreur_io)?;
+    let (debut, corps) = match contenu.strip_prefix("---\n") {
+        Some(corps) => ("---\n", corps),
+        None => ("", contenu.as_str())
+    };
+    /* Une base vide est enregistrée comme une table vide, `{}`, qui ne peut pas recevoir de clé. */
+    let corps = if corps.trim() == "{}" { "" } else { corps };
+    fs::write(chemin_base, format!("{debut}{}\n{corps}", ligne_version())).map_err(erreur_io)
+}
+
+/// Met un écrit d’une base de données au format `version` au format actuel. Renvoie `None` s’il l’est
+/// déjà. Le champ `version` que portaient les écrits dans les premières bases au format 2 l’emporte sur
+/// la version de la base, et il est retiré.
+pub fn migrer(ecrit: &Yaml, version: i64) -> Result<Option<Yaml>, ErrType> {
+    let mut hash = ecrit.as_hash()
+        .ok_or(ErrType::YamlParseError("Un écrit doit être une table.".to_string()))?.clone();
+    let version = match hash.remove(&cle("version")) {
+        None if version == VERSION => return Ok(None),
+        None => version,
+        Some(Yaml::Integer(version)) if (1..=VERSION).contains(&version) => version,
+        Some(_) => return Err(ErrType::YamlParseError("Version du format de l’écrit invalide.".to_string()))
+    };
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(&mut hash)?;
+    }
+    Ok(Some(Yaml::Hash(hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use fondabots_lib::yaml_rust2::YamlLoader;
+
+    use super::*;
+
+    fn ecrit(yaml: &str) -> Yaml {
+        YamlLoader::load_from_str(yaml).unwrap().remove(0)
+    }
+
+    #[test]
+    fn ecrit_v1_mis_au_format_actuel() {
+        let v1 = ecrit("nom: A\nlien: t-1\nlastUpdate: 1700000000\ninteresses:\n  - type: \"⊙ Exclusif\"\n  - type: \"Inconnu?\"\n  - type: collab");
+        let v2 = migrer(&v1, 1).unwrap().unwrap();
+        assert!(v2["lastUpdate"].is_badvalue());
+        assert_eq!(v2["last-update"].as_i64(), Some(1700000000));
+        let types: Vec<_> = v2["interesses"].as_vec().unwrap().iter().map(|interet| interet["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["seul", "longterme", "collab"]);
+        assert!(v2["version"].is_badvalue());
+    }
+
+    #[test]
+    fn ecrit_au_format_actuel_inchange() {
+        assert_eq!(migrer(&ecrit("nom: A\nlien: t-1\nlast-update: 1700000000"), VERSION).unwrap(), None);
+    }
+
+    #[test]
+    fn version_de_l_ecrit_retiree() {
+        let v2 = migrer(&ecrit("nom: A\nlien: t-1\nversion: 2\nlast-update: 1700000000"), 1).unwrap().unwrap();
+        assert!(v2["version"].is_badvalue());
+        assert_eq!(v2["last-update"].as_i64(), Some(1700000000));
+        assert!(migrer(&ecrit("nom: A\nversion: 3"), VERSION).is_err());
+    }
+
+    #[test]
+    fn version_de_la_base() {
+        assert_eq!(lire_version(&ecrit("14681522:\n  nom: A")).unwrap(), 1);
+        assert_eq!(lire_version(&ecrit("version: 2\n14681522:\n  nom: A")).unwrap(), 2);
+        assert!(lire_version(&ecrit("version: 3")).is_err());
+        assert!(lire_version(&ecrit("version: deux")).is_err());
+    }
+
+    #[test]
+    fn type_de_marque_inconnu_refuse() {
+        assert!(migrer(&ecrit("nom: A\ninteresses:\n  - type: Indécis"), 1).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use fondabots_lib::{Bot, ErrType, Object};
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use serenity::all::{ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, Context as SerenityContext,
+                    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter,
+                    CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
+                    CreateSelectMenuOption, Timestamp};
+use strum::IntoEnumIterator;
+
+use super::requete::Tri;
+use super::Ecrit;
+use crate::DataType;
+
+/// Nombre d’écrits affichés par page.
+const TAILLE_PAGE: usize = 10;
+
+/// Durée d’inactivité après laquelle une liste ne répond plus à ses boutons.
+const DUREE_NAVIGATEUR: Duration = Duration::from_secs(30 * 60);
+
+/// Liste de résultats parcourue page par page dans un seul message.
+struct Navigateur {
+    jeton: u64,
+    titre: String,
+    ids: Vec<u64>,
+    tri: Tri,
+    page: usize,
+    expiration: Instant,
+}
+
+static NAVIGATEURS: Mutex<Vec<Navigateur>> = Mutex::new(Vec::new());
+
+/// Tronque un texte à la longueur maximale des libellés de Discord.
+pub fn libelle(texte: &str) -> String {
+    if texte.chars().count() <= 100 {
+        texte.to_string()
+    } else {
+        texte.chars().take(99).collect::<String>() + "…"
+    }
+}
+
+impl Navigateur {
+    /// Construit la page courante de la liste : l’embed et les composants pour naviguer.
+    fn afficher(&mut self, bot: &Bot<Ecrit>) -> (CreateEmbed, Vec<CreateActionRow>) {
+        /* Les écrits supprimés depuis la recherche disparaissent de la liste. */
+        let resultats = self.tri.trier(self.ids.iter()
+            .filter_map(|id| bot.database.get(id).map(|ecrit| (id, ecrit)))
+            .collect());
+        let pages = resultats.len().div_ceil(TAILLE_PAGE).max(1);
+        self.page = self.page.min(pages - 1);
+        let page = &resultats[(self.page * TAILLE_PAGE).min(resultats.len())..((self.page + 1) * TAILLE_PAGE).min(resultats.len())];
+
+        let embed = CreateEmbed::new()
+            .author(CreateEmbedAuthor::new(self.titre.clone()))
+            .title("Résultats de la recherche")
+            .description(if page.is_empty() {
+                "Aucun résultat.".to_string()
+            } else {
+                page.iter().map(|(_, ecrit)| ecrit.get_list_entry()).collect::<String>()
+            })
+            .color(73887)
+            .footer(CreateEmbedFooter::new(format!("Page {}/{pages} · {} résultat(s) · Tri : {}", self.page + 1, resultats.len(), self.tri)))
+            .timestamp(Timestamp::now());
+
+        let mut composants = vec![
+            CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("nv-{}-p", self.jeton)).label("Précédent").style(ButtonStyle::Secondary)
+                    .disabled(self.page == 0),
+                CreateButton::new(format!("nv-{}-s", self.jeton)).label("Suivant").style(ButtonStyle::Secondary)
+                    .disabled(self.page + 1 >= pages),
+            ]),
+            CreateActionRow::SelectMenu(CreateSelectMenu::new(format!("nt-{}", self.jeton), CreateSelectMenuKind::String {
+                options: Tri::iter().map(|tri| CreateSelectMenuOption::new(format!("Trier par : {tri}"), tri.cle())
+                    .default_selection(tri == self.tri)).collect()
+            })),
+        ];
+        if !page.is_empty() {
+            composants.push(CreateActionRow::SelectMenu(CreateSelectMenu::new(format!("nc-{}", self.jeton), CreateSelectMenuKind::String {
+                options: page.iter().map(|(id, ecrit)| CreateSelectMenuOption::new(libelle(&ecrit.nom), id.to_string())
+                    .description(libelle(&format!("{} · {} · {}", ecrit.auteur, ecrit.status, ecrit.type_)))).collect()
+            }).placeholder("Afficher un écrit")));
+        }
+        (embed, composants)
+    }
+}
+
+impl Ecrit {
+    /// Répond à une commande par une liste de résultats interactive.
+    pub async fn naviguer(ctx: poise::Context<'_, DataType, ErrType>, bot: &Bot<Self>, titre: &str, ids: Vec<u64>, tri: Tri) -> Result<(), ErrType> {
+        if ids.is_empty() {
+            ctx.send(CreateReply::default().embed(CreateEmbed::new()
+                .title("Aucun résultat.")
+                .color(16001600)
+                .author(CreateEmbedAuthor::new(titre))
+                .timestamp(Timestamp::now()))).await?;
+            return Ok(());
+        }
+        let mut navigateur = Navigateur {
+            jeton: ctx.id(),
+            titre: titre.to_string(),
+            ids,
+            tri,
+            page: 0,
+            expiration: Instant::now() + DUREE_NAVIGATEUR,
+        };
+        let (embed, composants) = navigateur.afficher(bot);
+        {
+            let mut navigateurs = NAVIGATEURS.lock().unwrap();
+            navigateurs.retain(|navigateur| navigateur.expiration > Instant::now());
+            navigateurs.push(navigateur);
+        }
+        ctx.send(CreateReply::default().embed(embed).components(composants)).await?;
+        Ok(())
+    }
+
+    /// Composants d’une liste interactive : pages (`nv`), tri (`nt`) et choix d’un écrit (`nc`).
+    pub(super) async fn composant_navigateur(ctx: &SerenityContext, interaction: &ComponentInteraction, bot: &Bot<Self>,
+                                             type_: &str, jeton: u64, action: Option<&str>) -> Result<(), ErrType> {
+        let valeur = match &interaction.data.kind {
+            ComponentInteractionDataKind::StringSelect { values } => values.first().cloned(),
+            _ => None
+        };
+        let reponse = {
+            let mut navigateurs = NAVIGATEURS.lock().unwrap();
+            navigateurs.retain(|navigateur| navigateur.expiration > Instant::now());
+            match navigateurs.iter_mut().find(|navigateur| navigateur.jeton == jeton) {
+                None => CreateInteractionResponseMessage::new().content("Cette liste a expiré, relancez la commande.")
+                    .embeds(vec![]).components(vec![]),
+                Some(navigateur) => {
+                    navigateur.expiration = Instant::now() + DUREE_NAVIGATEUR;
+                    let ecrit = valeur.as_ref().filter(|_| type_ == "nc")
+                        .and_then(|id| id.parse::<u64>().ok())
+                        .and_then(|id| bot.database.get(&id));
+                    match (type_, action) {
+                        ("nv", Some("p")) => navigateur.page = navigateur.page.saturating_sub(1),
+                        ("nv", Some("s")) => navigateur.page += 1,
+                        ("nt", _) => {
+                            navigateur.tri = valeur.as_deref().and_then(Tri::from_cle).unwrap_or_default();
+                            navigateur.page = 0;
+                        }
+                        _ => ()
+                    }
+                    match ecrit {
+                        /* L’écrit choisi remplace la liste, avec un bouton pour y revenir. */
+                        Some(ecrit) => CreateInteractionResponseMessage::new().embed(ecrit.get_embed())
+                            .components(vec![ecrit.get_buttons(), CreateActionRow::Buttons(vec![
+                                CreateButton::new(format!("nv-{jeton}-l")).label("Retour à la liste").style(ButtonStyle::Secondary)
+                            ])]),
+                        None => {
+                            let (embed, composants) = navigateur.afficher(bot);
+                            CreateInteractionResponseMessage::new().embed(embed).components(composants)
+                        }
+                    }
+                }
+            }
+        };
+        interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(reponse)).await?;
+        Ok(())
+    }
+}
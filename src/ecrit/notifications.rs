@@ -0,0 +1,180 @@
+use fondabots_lib::yaml_rust2::{yaml, Yaml};
+use poise::{serenity_prelude as serenity, ChoiceParameter};
+use serenity::all::{CreateMessage, UserId};
+
+use super::auteurs;
+use super::fields::Status;
+use super::historique::Acteur;
+use super::Ecrit;
+
+/// Endroit où un auteur reçoit les notifications concernant ses écrits.
+#[derive(ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Destination {
+    #[name = "Désactivées"]
+    Aucune,
+    #[name = "Message privé"]
+    MessagePrive,
+    #[name = "Fil Discord de l’écrit"]
+    Fil,
+}
+
+impl Destination {
+    pub fn cle(&self) -> &'static str {
+        match self {
+            Destination::Aucune => "aucune",
+            Destination::MessagePrive => "mp",
+            Destination::Fil => "fil",
+        }
+    }
+
+    pub fn from_cle(cle: &str) -> Option<Self> {
+        [Destination::Aucune, Destination::MessagePrive, Destination::Fil].into_iter()
+            .find(|destination| destination.cle() == cle)
+    }
+}
+
+/// Événements pouvant être notifiés à l’auteur d’un écrit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TypeNotification {
+    Critique,
+    Marque,
+    Validation,
+    Refus,
+    Archivage,
+}
+
+impl TypeNotification {
+    pub const TOUS: [TypeNotification; 5] = [TypeNotification::Critique, TypeNotification::Marque,
+        TypeNotification::Validation, TypeNotification::Refus, TypeNotification::Archivage];
+
+    pub fn cle(&self) -> &'static str {
+        match self {
+            TypeNotification::Critique => "critique",
+            TypeNotification::Marque => "marque",
+            TypeNotification::Validation => "validation",
+            TypeNotification::Refus => "refus",
+            TypeNotification::Archivage => "archivage",
+        }
+    }
+
+    pub fn from_cle(cle: &str) -> Option<Self> {
+        Self::TOUS.into_iter().find(|type_| type_.cle() == cle)
+    }
+}
+
+/// Préférences de notification d’un auteur, enregistrées dans le registre des auteurs. Les
+/// notifications sont désactivées par défaut.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Preferences {
+    pub destination: Destination,
+    pub evenements: Vec<TypeNotification>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            destination: Destination::Aucune,
+            evenements: TypeNotification::TOUS.to_vec(),
+        }
+    }
+}
+
+impl Preferences {
+    pub fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            destination: data["destination"].as_str().and_then(Destination::from_cle).unwrap_or(defaut.destination),
+            evenements: data["evenements"].as_vec()
+                .map(|evenements| evenements.iter().filter_map(|cle| cle.as_str().and_then(TypeNotification::from_cle)).collect())
+                .unwrap_or(defaut.evenements),
+        }
+    }
+
+    pub fn serialize(&self) -> Yaml {
+        let mut hash = yaml::Hash::new();
+        hash.insert(Yaml::String("destination".to_string()), Yaml::String(self.destination.cle().to_string()));
+        hash.insert(Yaml::String("evenements".to_string()), Yaml::Array(
+            self.evenements.iter().map(|type_| Yaml::String(type_.cle().to_string())).collect()));
+        Yaml::Hash(hash)
+    }
+
+    pub fn active(&self, type_: TypeNotification) -> bool {
+        self.destination != Destination::Aucune && self.evenements.contains(&type_)
+    }
+}
+
+impl Ecrit {
+    /// Notifie l’auteur de l’écrit, s’il est inscrit et l’a demandé pour ce type d’événement. À appeler
+    /// une fois le changement effectué. L’envoi est lancé en arrière-plan : l’appelant n’attend pas
+    /// Discord et garde le verrou du bot.
+    pub fn notifier(&self, type_: TypeNotification, message: String) {
+        let Some(auteurs::Auteur { membre, notifications: preferences, .. }) = self.membre_auteur.and_then(auteurs::auteur) else {
+            return;
+        };
+        if !preferences.active(type_) {
+            return;
+        }
+        /* L’annonce d’archivage est déjà postée dans le fil de l’écrit. */
+        if type_ == TypeNotification::Archivage && preferences.destination == Destination::Fil && self.discord_chan.is_some() {
+            return;
+        }
+        let Some(ctx) = crate::contexte().cloned() else {
+            return;
+        };
+        let (fil, destination, id) = (self.discord_chan, preferences.destination, self.id);
+        let message = format!("**{}** : {message}", self.nom);
+        tokio::spawn(async move {
+            let membre = UserId::new(membre);
+            let envoye = match (destination, fil) {
+                (Destination::Fil, Some(fil)) => fil.send_message(&ctx, CreateMessage::new().content(format!("<@{membre}> {message}"))).await.is_ok(),
+                /* Sans fil Discord lié, la notification est envoyée en message privé. */
+                _ => membre.direct_message(&ctx, CreateMessage::new().content(message)).await.is_ok()
+            };
+            if !envoye {
+                eprintln!("Impossible de notifier le membre {membre} pour l’écrit {id}.");
+            }
+        });
+    }
+
+    /// Notifie l’auteur que son écrit vient d’être critiqué.
+    pub fn notifier_critique(&self, acteur: &Acteur) {
+        self.notifier(TypeNotification::Critique, format!("votre écrit a été critiqué par {acteur}."));
+    }
+
+    /// Notifie l’auteur que son écrit vient d’être validé ou refusé. Sans effet pour les autres statuts.
+    pub fn notifier_statut(&self, acteur: &Acteur) {
+        match self.status {
+            Status::Valide => self.notifier(TypeNotification::Validation, format!("votre écrit a été validé par {acteur}.")),
+            Status::Refuse => self.notifier(TypeNotification::Refus, format!("votre écrit a été refusé par {acteur}.")),
+            _ => ()
+        }
+    }
+
+    /// Notifie l’auteur de la marque d’intérêt qui vient d’être posée sur son écrit.
+    pub fn notifier_marque(&self) {
+        if let Some(interet) = self.interesses.last() {
+            self.notifier(TypeNotification::Marque, format!("votre écrit a reçu une marque d’intérêt {}.", interet.description()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fondabots_lib::yaml_rust2::YamlLoader;
+
+    use super::*;
+
+    #[test]
+    fn preferences_relues() {
+        let preferences = Preferences {
+            destination: Destination::MessagePrive,
+            evenements: vec![TypeNotification::Critique, TypeNotification::Archivage],
+        };
+        let relues = Preferences::from_yaml(&preferences.serialize());
+        assert_eq!(relues, preferences);
+        assert!(relues.active(TypeNotification::Critique));
+        assert!(!relues.active(TypeNotification::Marque));
+        assert_eq!(Preferences::from_yaml(&YamlLoader::load_from_str("destination: fil").unwrap()[0]).evenements, TypeNotification::TOUS);
+        assert!(!Preferences::default().active(TypeNotification::Critique));
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use chrono::Utc;
+use fondabots_lib::yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+use fondabots_lib::{ErrType, Object};
+
+use super::{migrations, Ecrit};
+
+/// Un enregistrement d’écrit est une table possédant un lien.
+fn est_ecrit(yaml: &Yaml) -> bool {
+    matches!(yaml, Yaml::Hash(hash) if hash.contains_key(&Yaml::String("lien".to_string())))
+}
+
+/// Met récursivement les écrits du document au format actuel et en retire les écrits illisibles, en
+/// les ajoutant aux rejets avec la raison. `version` est celle du format de la base. Renvoie le nombre
+/// d’écrits migrés.
+fn trier(yaml: &mut Yaml, version: i64, rejets: &mut Vec<(String, Yaml)>) -> usize {
+    let mut migres = 0;
+    let mut garder = |element: &mut Yaml| -> bool {
+        if !est_ecrit(element) {
+            migres += trier(element, version, rejets);
+            return true;
+        }
+        match migrations::migrer(element, version).and_then(|migre| {
+            Ecrit::from_yaml(migre.as_ref().unwrap_or(element))?;
+            Ok(migre)
+        }) {
+            Ok(Some(migre)) => {
+                *element = migre;
+                migres += 1;
+                true
+            }
+            Ok(None) => true,
+            Err(e) => {
+                let nom = element["nom"].as_str().or(element["lien"].as_str()).unwrap_or("?");
+                rejets.push((format!("{nom} : {e}"), element.clone()));
+                false
+            }
+        }
+    };
+    match yaml {
+        Yaml::Array(elements) => elements.retain_mut(garder),
+        Yaml::Hash(hash) => hash.retain_with_order(|_, valeur| garder(valeur)),
+        _ => ()
+    }
+    migres
+}
+
+fn emettre(yaml: &Yaml) -> Result<String, ErrType> {
+    let mut sortie = String::new();
+    YamlEmitter::new(&mut sortie).dump(yaml)
+        .map_err(|e| ErrType::YamlParseError(format!("Impossible d’écrire le yaml : {e}")))?;
+    Ok(sortie)
+}
+
+/// Prépare la base de données avant son chargement par la bibliothèque. Les écrits d’une version
+/// précédente du format sont migrés, les écrits illisibles sont retirés et ajoutés au fichier de
+/// quarantaine à côté de la base, pour être corrigés à la main plutôt que d’empêcher le démarrage. La
+/// clé `version`, que la bibliothèque prendrait pour un écrit, est retirée : elle est inscrite de nouveau
+/// par [`migrations::inscrire_version`] une fois la base chargée. Renvoie le résumé à journaliser s’il y
+/// a eu des changements.
+pub fn preparer_base(chemin_base: &str) -> Result<Option<String>, ErrType> {
+    let erreur_io = |e: std::io::Error| ErrType::YamlParseError(format!("Base de données {chemin_base} : {e}"));
+    let contenu = match fs::read_to_string(chemin_base) {
+        Ok(contenu) => contenu,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(erreur_io(e))
+    };
+    let mut docs = YamlLoader::load_from_str(contenu.as_str())
+        .map_err(|e| ErrType::YamlParseError(format!("Base de données {chemin_base} illisible : {e}")))?;
+    let version = docs.first().map_or(Ok(migrations::VERSION), migrations::lire_version)?;
+    let avait_version = match docs.first_mut() {
+        Some(Yaml::Hash(base)) => base.remove(&Yaml::String("version".to_string())).is_some(),
+        _ => false
+    };
+    let mut rejets = Vec::new();
+    let migres: usize = docs.iter_mut().map(|doc| trier(doc, version, &mut rejets)).sum();
+    if rejets.is_empty() && migres == 0 {
+        if avait_version {
+            fs::write(chemin_base, docs.iter().map(emettre).collect::<Result<Vec<_>, ErrType>>()?.join("\n") + "\n")
+                .map_err(erreur_io)?;
+        }
+        return Ok(None);
+    }
+
+    let mut resume = Vec::new();
+    if migres > 0 {
+        resume.push(format!("{migres} écrit(s) mis au format {} de la base de données.", migrations::VERSION));
+    }
+    if !rejets.is_empty() {
+        let chemin_quarantaine = Path::new(chemin_base).with_extension("quarantaine.yml");
+        let mut quarantaine = OpenOptions::new().create(true).append(true).open(&chemin_quarantaine).map_err(erreur_io)?;
+        writeln!(quarantaine, "# Mis en quarantaine le {}", Utc::now().format("%d/%m/%Y à %H:%M")).map_err(erreur_io)?;
+        writeln!(quarantaine, "{}", emettre(&Yaml::Array(rejets.iter().map(|(_, ecrit)| ecrit.clone()).collect()))?)
+            .map_err(erreur_io)?;
+        resume.push(format!("{} écrit(s) illisible(s) retiré(s) de la base de données et placé(s) dans {} :\n{}",
+                            rejets.len(), chemin_quarantaine.display(),
+                            rejets.iter().map(|(raison, _)| format!("- {raison}")).collect::<Vec<_>>().join("\n")));
+    }
+    fs::write(chemin_base, docs.iter().map(emettre).collect::<Result<Vec<_>, ErrType>>()?.join("\n") + "\n")
+        .map_err(erreur_io)?;
+
+    Ok(Some(resume.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Base de données enregistrée par une version du bot antérieure aux versions du format.
+    const BASE_V1: &str = include_str!("../../tests/fixtures/critibot.v1.yml");
+
+    /// Copie la base dans un dossier propre au test et renvoie le chemin de la copie.
+    fn copier_base(test: &str, contenu: &str) -> PathBuf {
+        let dossier = std::env::temp_dir().join(format!("critibot-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dossier);
+        fs::create_dir_all(&dossier).unwrap();
+        let chemin = dossier.join("critibot.yml");
+        fs::write(&chemin, contenu).unwrap();
+        chemin
+    }
+
+    fn lire(chemin: &Path) -> Yaml {
+        YamlLoader::load_from_str(fs::read_to_string(chemin).unwrap().as_str()).unwrap().remove(0)
+    }
+
+    #[test]
+    fn base_v1_migree_et_ecrit_illisible_en_quarantaine() {
+        let chemin = copier_base("v1", BASE_V1);
+        let chemin_base = chemin.to_str().unwrap();
+
+        let resume = preparer_base(chemin_base).unwrap().unwrap();
+        assert!(resume.contains("2 écrit(s) mis au format 2"), "{resume}");
+        assert!(resume.contains("1 écrit(s) illisible(s)"), "{resume}");
+        assert!(resume.contains("Brouillon sans statut connu"), "{resume}");
+
+        let base = lire(&chemin);
+        let mur = &base[14681522];
+        assert_eq!(mur["last-update"].as_i64(), Some(1700000000));
+        assert!(mur["lastUpdate"].is_badvalue());
+        assert!(mur["version"].is_badvalue());
+        assert_eq!(mur["interesses"][0]["type"].as_str(), Some("seul"));
+        assert_eq!(mur["interesses"][1]["type"].as_str(), Some("longterme"));
+        assert_eq!(base[14702210]["interesses"][0]["type"].as_str(), Some("collab"));
+        assert!(base[14711111].is_badvalue());
+        for id in [14681522, 14702210] {
+            Ecrit::from_yaml(&base[id]).unwrap();
+        }
+        /* La clé de version est retirée pour le chargement de la base, puis remise. */
+        assert!(base["version"].is_badvalue());
+        migrations::inscrire_version(chemin_base).unwrap();
+        assert_eq!(migrations::lire_version(&lire(&chemin)).unwrap(), migrations::VERSION);
+        assert_eq!(lire(&chemin)[14681522], base[14681522]);
+
+        let quarantaine = fs::read_to_string(chemin.with_extension("quarantaine.yml")).unwrap();
+        assert!(quarantaine.starts_with("# Mis en quarantaine le "));
+        let rejets = YamlLoader::load_from_str(quarantaine.as_str()).unwrap().remove(0);
+        assert_eq!(rejets.as_vec().map(Vec::len), Some(1));
+        assert_eq!(rejets[0]["nom"].as_str(), Some("Brouillon sans statut connu"));
+        /* L’écrit est mis en quarantaine tel qu’il était dans la base. */
+        assert_eq!(rejets[0]["lastUpdate"].as_i64(), Some(1700400000));
+
+        /* Une base déjà migrée n’est plus migrée : seule sa version est retirée. */
+        assert_eq!(preparer_base(chemin_base).unwrap(), None);
+        assert_eq!(lire(&chemin), base);
+    }
+
+    #[test]
+    fn version_lue_dans_la_base() {
+        /* Une base marquée au format actuel n’est pas migrée, même si ses écrits sont encore au format 1. */
+        let chemin = copier_base("version", &BASE_V1.replacen("---\n", "---\nversion: 2\n", 1));
+        let resume = preparer_base(chemin.to_str().unwrap()).unwrap().unwrap();
+        assert!(!resume.contains("mis au format"), "{resume}");
+        assert!(resume.contains("3 écrit(s) illisible(s)"), "{resume}");
+    }
+
+    #[test]
+    fn version_inscrite_une_seule_fois() {
+        let chemin = copier_base("inscription", "---\n{}\n");
+        let chemin_base = chemin.to_str().unwrap();
+        migrations::inscrire_version(chemin_base).unwrap();
+        migrations::inscrire_version(chemin_base).unwrap();
+        assert_eq!(fs::read_to_string(&chemin).unwrap(), "---\nversion: 2\n");
+        assert_eq!(preparer_base(chemin_base).unwrap(), None);
+        assert!(lire(&chemin)["version"].is_badvalue());
+    }
+
+    #[test]
+    fn nouvelle_base_au_format_actuel() {
+        let chemin = copier_base("nouvelle", "");
+        fs::remove_file(&chemin).unwrap();
+        let chemin_base = chemin.to_str().unwrap();
+        assert_eq!(preparer_base(chemin_base).unwrap(), None);
+        assert!(!chemin.exists());
+    }
+
+    #[test]
+    fn base_plus_recente_refusee() {
+        let chemin = copier_base("recente", BASE_V1);
+        let chemin_base = chemin.to_str().unwrap();
+        let recente = BASE_V1.replacen("---\n", "---\nversion: 3\n", 1);
+        fs::write(&chemin, &recente).unwrap();
+        assert!(preparer_base(chemin_base).is_err());
+        assert_eq!(fs::read_to_string(&chemin).unwrap(), recente);
+    }
+}
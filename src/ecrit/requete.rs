@@ -0,0 +1,394 @@
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use fondabots_lib::tools::{self, basicize, parse_date};
+use fondabots_lib::Bot;
+use serenity::all::Timestamp;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use super::fields::{Status, Type};
+use super::{Ecrit, ModeTags};
+
+/// Clés reconnues dans une requête, pour les messages d’erreur.
+const CLES: &str = "nom, statut, type, auteur, tag, avant, après, tri";
+
+/// Ordre des résultats d’une requête.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, EnumIter)]
+pub enum Tri {
+    /// Par date de dernière modification.
+    #[default]
+    Date,
+    Nom,
+    Auteur,
+    /// Du plus marqué au moins marqué.
+    Marques,
+}
+
+impl Tri {
+    /// Clé du tri dans les requêtes et les identifiants de composants.
+    pub fn cle(&self) -> &'static str {
+        match self {
+            Tri::Date => "date",
+            Tri::Nom => "nom",
+            Tri::Auteur => "auteur",
+            Tri::Marques => "marques",
+        }
+    }
+
+    pub fn from_cle(cle: &str) -> Option<Self> {
+        Self::iter().find(|tri| tri.cle() == cle)
+    }
+
+    /// Trie des résultats. Les tris autres que par date départagent les égalités par date.
+    pub fn trier<'a>(&self, resultats: Vec<(&'a u64, &'a Ecrit)>) -> Vec<(&'a u64, &'a Ecrit)> {
+        let mut resultats = tools::sort_by_date(resultats);
+        match self {
+            Tri::Date => (),
+            Tri::Nom => resultats.sort_by_cached_key(|(_, ecrit)| basicize(&ecrit.nom)),
+            Tri::Auteur => resultats.sort_by_cached_key(|(_, ecrit)| basicize(&ecrit.auteur)),
+            Tri::Marques => resultats.sort_by_key(|(_, ecrit)| Reverse(ecrit.interesses.len())),
+        }
+        resultats
+    }
+}
+
+impl Display for Tri {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Tri::Date => "Date de modification",
+            Tri::Nom => "Nom",
+            Tri::Auteur => "Auteur",
+            Tri::Marques => "Nombre de marques",
+        })
+    }
+}
+
+/// Un critère élémentaire. Les valeurs d’une même liste sont alternatives.
+#[derive(Clone, PartialEq, Debug)]
+enum Critere {
+    Nom(String),
+    Statuts(Vec<Status>),
+    Types(Vec<Type>),
+    Auteurs(Vec<String>),
+    Tags(Vec<String>, ModeTags),
+    Avant(Timestamp),
+    Apres(Timestamp),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Condition {
+    critere: Critere,
+    negation: bool,
+}
+
+/// Requête de `/ulister`, de la forme `statut:ouvert,ouvert* type:rapport -tag:humour auteur:"Dr Foo"`.
+///
+/// Les termes séparés par des espaces doivent tous être vérifiés, les valeurs séparées par des virgules
+/// sont alternatives et un `-` devant un terme l’inverse. Des tags séparés par des `+` sont tous exigés. Le mot-clé `ou` sépare des groupes de termes
+/// dont un seul doit être vérifié. Un mot sans clé est recherché dans le nom de l’écrit.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Requete {
+    groupes: Vec<Vec<Condition>>,
+    pub tri: Tri,
+}
+
+/// Terme de la requête avant interprétation.
+struct Jeton {
+    /// Position du premier caractère du terme dans la requête, à partir de 1.
+    position: usize,
+    brut: String,
+    negation: bool,
+    cle: Option<String>,
+    valeurs: Vec<String>,
+    /// Le terme contient des guillemets, il n’est donc jamais un mot-clé.
+    cite: bool,
+}
+
+impl Jeton {
+    fn erreur(&self, message: impl AsRef<str>) -> String {
+        format!("Erreur au terme `{}` (caractère {}) : {}", self.brut, self.position, message.as_ref())
+    }
+
+    fn valeur_unique(&self) -> Result<&String, String> {
+        match self.valeurs.as_slice() {
+            [valeur] => Ok(valeur),
+            _ => Err(self.erreur("une seule valeur est attendue."))
+        }
+    }
+
+    fn date(&self) -> Result<Timestamp, String> {
+        let valeur = self.valeur_unique()?;
+        parse_date(valeur.clone()).ok_or(self.erreur(format!("« {valeur} » n’est pas une date au format jj/mm/aaaa.")))
+    }
+}
+
+/// Découpe la requête en termes séparés par des espaces, hors guillemets.
+fn decouper(requete: &str) -> Result<Vec<Jeton>, String> {
+    let caracteres: Vec<char> = requete.chars().collect();
+    let mut jetons = Vec::new();
+    let mut i = 0;
+    while i < caracteres.len() {
+        if caracteres[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let mut jeton = Jeton {
+            position: i + 1,
+            brut: String::new(),
+            negation: caracteres[i] == '-',
+            cle: None,
+            valeurs: Vec::new(),
+            cite: false,
+        };
+        if jeton.negation {
+            jeton.brut.push('-');
+            i += 1;
+        }
+        let mut courant = String::new();
+        let mut guillemets = false;
+        while i < caracteres.len() && (guillemets || !caracteres[i].is_whitespace()) {
+            let c = caracteres[i];
+            jeton.brut.push(c);
+            match c {
+                '"' => {
+                    guillemets = !guillemets;
+                    jeton.cite = true;
+                }
+                ':' if !guillemets && jeton.cle.is_none() => jeton.cle = Some(std::mem::take(&mut courant)),
+                ',' if !guillemets && jeton.cle.is_some() => jeton.valeurs.push(std::mem::take(&mut courant)),
+                c => courant.push(c)
+            }
+            i += 1;
+        }
+        jeton.valeurs.push(courant);
+        if guillemets {
+            return Err(jeton.erreur("guillemet non fermé."));
+        }
+        jetons.push(jeton);
+    }
+    Ok(jetons)
+}
+
+/// Lit une liste de valeurs avec `FromStr`, en signalant la première valeur invalide.
+fn lire_valeurs<T: FromStr>(jeton: &Jeton, genre: &str) -> Result<Vec<T>, String> {
+    jeton.valeurs.iter()
+        .map(|valeur| T::from_str(valeur).map_err(|_| jeton.erreur(format!("{genre} « {valeur} » inexistant."))))
+        .collect()
+}
+
+/// Lit les tags d’un terme : `tag:a,b` demande l’un des tags, `tag:a+b` tous les tags et `-tag:a,b`
+/// aucun des tags. Renvoie aussi si la condition reste inversée.
+fn lire_tags(jeton: &Jeton) -> Result<(Critere, bool), String> {
+    match jeton.valeurs.as_slice() {
+        [valeur] if valeur.contains('+') => {
+            let tags: Vec<String> = valeur.split('+').map(|tag| tag.trim().to_string()).collect();
+            if tags.iter().any(String::is_empty) {
+                return Err(jeton.erreur("tag vide."));
+            }
+            Ok((Critere::Tags(tags, ModeTags::Tous), jeton.negation))
+        }
+        valeurs if valeurs.iter().any(|valeur| valeur.contains('+')) =>
+            Err(jeton.erreur("les tags se séparent soit par des virgules (l’un des tags), soit par des + (tous les tags).")),
+        valeurs if jeton.negation => Ok((Critere::Tags(valeurs.to_vec(), ModeTags::Aucun), false)),
+        valeurs => Ok((Critere::Tags(valeurs.to_vec(), ModeTags::Un), false))
+    }
+}
+
+/// Un auteur correspond au critère si chaque mot du critère est inclus dans l’un des mots de son nom.
+fn auteur_correspond(auteur: &str, critere: &str) -> bool {
+    critere.split_whitespace().all(|mot_critere|
+        auteur.split_whitespace().any(|mot_auteur| basicize(mot_auteur).contains(&basicize(mot_critere))))
+}
+
+impl Requete {
+    /// Analyse une requête. L’erreur renvoyée désigne le terme fautif et sa position.
+    pub fn analyser(requete: &str) -> Result<Self, String> {
+        let mut resultat = Self { groupes: vec![Vec::new()], tri: Tri::default() };
+        let mut tri_donne = false;
+        let jetons = decouper(requete)?;
+        for jeton in &jetons {
+            if jeton.cle.is_none() && !jeton.cite && !jeton.negation && (basicize(&jeton.brut) == "ou" || jeton.brut == "|") {
+                if resultat.groupes.last().is_some_and(|groupe| groupe.is_empty()) {
+                    return Err(jeton.erreur("« ou » doit séparer deux groupes de termes."));
+                }
+                resultat.groupes.push(Vec::new());
+                continue;
+            }
+            if jeton.valeurs.iter().any(|valeur| valeur.trim().is_empty()) {
+                return Err(jeton.erreur("valeur vide."));
+            }
+            let mut negation = jeton.negation;
+            let critere = match jeton.cle.as_deref().map(basicize).as_deref() {
+                None | Some("nom") => Critere::Nom(basicize(&jeton.valeurs.join(","))),
+                Some("statut" | "statuts") => Critere::Statuts(lire_valeurs(jeton, "statut")?),
+                Some("type" | "types") => Critere::Types(lire_valeurs(jeton, "type")?),
+                Some("auteur" | "auteurs") => Critere::Auteurs(jeton.valeurs.clone()),
+                Some("tag" | "tags") => {
+                    let (critere, inverse) = lire_tags(jeton)?;
+                    negation = inverse;
+                    critere
+                }
+                Some("avant") => Critere::Avant(jeton.date()?),
+                Some("apres") => Critere::Apres(jeton.date()?),
+                Some("tri") => {
+                    if jeton.negation {
+                        return Err(jeton.erreur("le tri ne peut pas être inversé."));
+                    }
+                    if tri_donne {
+                        return Err(jeton.erreur("le tri est déjà donné."));
+                    }
+                    let valeur = jeton.valeur_unique()?;
+                    resultat.tri = Tri::from_cle(basicize(valeur).as_str())
+                        .ok_or(jeton.erreur(format!("tri « {valeur} » inconnu, utilisez date, nom, auteur ou marques.")))?;
+                    tri_donne = true;
+                    continue;
+                }
+                Some(_) => return Err(jeton.erreur(format!("clé inconnue. Les clés possibles sont : {CLES}.")))
+            };
+            resultat.groupes.last_mut().unwrap().push(Condition { critere, negation });
+        }
+        if resultat.groupes.len() > 1 && resultat.groupes.last().is_some_and(|groupe| groupe.is_empty()) {
+            return Err(format!("Erreur en fin de requête (caractère {}) : « ou » doit séparer deux groupes de termes.",
+                               requete.chars().count()));
+        }
+        Ok(resultat)
+    }
+
+    /// Vrai si la requête ne filtre aucun écrit.
+    pub fn est_vide(&self) -> bool {
+        self.groupes.iter().all(|groupe| groupe.is_empty())
+    }
+
+    fn verifie(condition: &Condition, ecrit: &Ecrit) -> bool {
+        condition.negation != match &condition.critere {
+            Critere::Nom(nom) => basicize(&ecrit.nom).contains(nom.as_str()),
+            Critere::Statuts(statuts) => statuts.contains(&ecrit.status),
+            Critere::Types(types) => types.contains(&ecrit.type_),
+            Critere::Auteurs(auteurs) => auteurs.iter().any(|auteur| auteur_correspond(&ecrit.auteur, auteur)),
+            Critere::Tags(tags, mode) => ecrit.correspond_tags(tags, *mode),
+            Critere::Avant(date) => ecrit.last_update < *date,
+            Critere::Apres(date) => ecrit.last_update > *date,
+        }
+    }
+
+    /// Vrai si l’écrit vérifie tous les termes d’au moins un groupe.
+    pub fn correspond(&self, ecrit: &Ecrit) -> bool {
+        self.groupes.iter().any(|groupe| groupe.iter().all(|condition| Self::verifie(condition, ecrit)))
+    }
+
+    /// Renvoie les écrits correspondant à la requête, dans l’ordre demandé.
+    pub fn executer<'a>(&self, bot: &'a Bot<Ecrit>) -> Vec<(&'a u64, &'a Ecrit)> {
+        self.tri.trier(bot.database.iter().filter(|(_, ecrit)| self.correspond(ecrit)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(critere: Critere, negation: bool) -> Condition {
+        Condition { critere, negation }
+    }
+
+    fn ecrit(nom: &str, auteur: &str, status: Status, tags: &[&str]) -> Ecrit {
+        let mut ecrit = Ecrit::new(nom.to_string(), "http://fondationscp.wikidot.com/forum/t-1/".to_string(),
+                                   Type::Rapport, status, auteur.to_string()).unwrap();
+        ecrit.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        ecrit
+    }
+
+    #[test]
+    fn termes_et_valeurs_alternatives() {
+        let requete = Requete::analyser("statut:ouvert,ouvert* type:rapport dent").unwrap();
+        assert_eq!(requete.groupes, vec![vec![
+            condition(Critere::Statuts(vec![Status::Ouvert, Status::OuvertPlus]), false),
+            condition(Critere::Types(vec![Type::Rapport]), false),
+            condition(Critere::Nom("dent".to_string()), false),
+        ]]);
+        assert_eq!(requete.tri, Tri::Date);
+    }
+
+    #[test]
+    fn negation() {
+        let requete = Requete::analyser("-tag:humour -statut:refusé").unwrap();
+        assert_eq!(requete.groupes, vec![vec![
+            condition(Critere::Tags(vec!["humour".to_string()], ModeTags::Aucun), false),
+            condition(Critere::Statuts(vec![Status::Refuse]), true),
+        ]]);
+        assert!(requete.correspond(&ecrit("Le Mur", "Dr Foo", Status::Ouvert, &["Humanoïde"])));
+        assert!(!requete.correspond(&ecrit("Le Mur", "Dr Foo", Status::Ouvert, &["Humour"])));
+        assert!(!requete.correspond(&ecrit("Le Mur", "Dr Foo", Status::Refuse, &[])));
+    }
+
+    #[test]
+    fn modes_des_tags() {
+        let requete = Requete::analyser("tag:humour,conte tag:humour+euclide -tag:a+b").unwrap();
+        assert_eq!(requete.groupes, vec![vec![
+            condition(Critere::Tags(vec!["humour".to_string(), "conte".to_string()], ModeTags::Un), false),
+            condition(Critere::Tags(vec!["humour".to_string(), "euclide".to_string()], ModeTags::Tous), false),
+            condition(Critere::Tags(vec!["a".to_string(), "b".to_string()], ModeTags::Tous), true),
+        ]]);
+        let requete = Requete::analyser("tag:humour+euclide").unwrap();
+        assert!(requete.correspond(&ecrit("A", "Bar", Status::Ouvert, &["Humour", "Euclide"])));
+        assert!(!requete.correspond(&ecrit("A", "Bar", Status::Ouvert, &["Humour"])));
+        erreur("tag:a+b,c", "tag:a+b,c", 1);
+        erreur("tag:a+", "tag:a+", 1);
+    }
+
+    #[test]
+    fn groupes_ou() {
+        let requete = Requete::analyser("statut:ouvert ou auteur:foo | tag:humour").unwrap();
+        assert_eq!(requete.groupes.len(), 3);
+        assert!(requete.correspond(&ecrit("A", "Bar", Status::Ouvert, &[])));
+        assert!(requete.correspond(&ecrit("A", "Dr Foo", Status::EnPause, &[])));
+        assert!(requete.correspond(&ecrit("A", "Bar", Status::EnPause, &["Humour"])));
+        assert!(!requete.correspond(&ecrit("A", "Bar", Status::EnPause, &[])));
+    }
+
+    #[test]
+    fn termes_entre_guillemets() {
+        let requete = Requete::analyser("auteur:\"Dr Foo\" \"ou\" nom:\"mur, dents\"").unwrap();
+        assert_eq!(requete.groupes, vec![vec![
+            condition(Critere::Auteurs(vec!["Dr Foo".to_string()]), false),
+            /* Un « ou » entre guillemets est un mot recherché, pas un séparateur. */
+            condition(Critere::Nom("ou".to_string()), false),
+            condition(Critere::Nom("mur, dents".to_string()), false),
+        ]]);
+    }
+
+    #[test]
+    fn tri() {
+        assert_eq!(Requete::analyser("statut:ouvert tri:marques").unwrap().tri, Tri::Marques);
+        assert_eq!(Requete::analyser("tri:Nom").unwrap().tri, Tri::Nom);
+        assert!(Requete::analyser("tri:nom").unwrap().est_vide());
+    }
+
+    /// Vérifie que l’analyse échoue sur le terme donné, à la position donnée.
+    fn erreur(requete: &str, terme: &str, position: usize) -> String {
+        let erreur = Requete::analyser(requete).unwrap_err();
+        assert!(erreur.starts_with(&format!("Erreur au terme `{terme}` (caractère {position}) : ")), "{erreur}");
+        erreur
+    }
+
+    #[test]
+    fn erreurs_sur_le_terme_fautif() {
+        assert!(erreur("statut:ouvert couleur:rouge", "couleur:rouge", 15).contains(CLES));
+        assert!(erreur("type:rapport statut:ouvert,fini", "statut:ouvert,fini", 14).contains("« fini »"));
+        erreur("nom:mur tri:nom tri:date", "tri:date", 17);
+        erreur("-tri:nom", "-tri:nom", 1);
+        assert!(erreur("tri:taille", "tri:taille", 1).contains("« taille »"));
+        erreur("avant:31/02/2024", "avant:31/02/2024", 1);
+        erreur("statut:ouvert ou ou type:conte", "ou", 18);
+        erreur("ou statut:ouvert", "ou", 1);
+        erreur("tag:", "tag:", 1);
+        erreur("nom:mur auteur:\"Dr Foo", "auteur:\"Dr Foo", 9);
+    }
+
+    #[test]
+    fn ou_en_fin_de_requete() {
+        assert_eq!(Requete::analyser("statut:ouvert ou").unwrap_err(),
+                   "Erreur en fin de requête (caractère 16) : « ou » doit séparer deux groupes de termes.");
+    }
+}
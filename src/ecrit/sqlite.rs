@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use fondabots_lib::yaml_rust2::{Yaml, YamlLoader};
+use fondabots_lib::{Bot, ErrType, Object};
+use poise::serenity_prelude as serenity;
+use rusqlite::{params, Connection, Transaction};
+use serenity::all::{ChannelId, Timestamp};
+use serenity::model::timestamp::InvalidTimestamp;
+
+use super::fields::{Interet, InteretType, Status, Type};
+use super::historique::{Acteur, Action, EntreeHistorique};
+use super::Ecrit;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS ecrits (
+        id INTEGER PRIMARY KEY,
+        nom TEXT NOT NULL,
+        lien TEXT NOT NULL,
+        type TEXT NOT NULL,
+        statut TEXT NOT NULL,
+        auteur TEXT NOT NULL,
+        derniere_maj INTEGER NOT NULL,
+        salon_discord INTEGER,
+        flux TEXT,
+        membre_auteur INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS marques (
+        ecrit INTEGER NOT NULL REFERENCES ecrits(id) ON DELETE CASCADE,
+        nom TEXT NOT NULL,
+        membre INTEGER NOT NULL,
+        date INTEGER NOT NULL,
+        type TEXT NOT NULL,
+        rappel INTEGER,
+        prolongee INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        ecrit INTEGER NOT NULL REFERENCES ecrits(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (ecrit, tag)
+    );
+    CREATE TABLE IF NOT EXISTS historique (
+        ecrit INTEGER NOT NULL REFERENCES ecrits(id) ON DELETE CASCADE,
+        rang INTEGER NOT NULL,
+        date INTEGER NOT NULL,
+        acteur_id INTEGER NOT NULL,
+        acteur TEXT,
+        action TEXT NOT NULL,
+        ancien TEXT,
+        nouveau TEXT,
+        PRIMARY KEY (ecrit, rang)
+    );
+    PRAGMA foreign_keys = ON;
+";
+
+/// Base SQLite ouverte au démarrage, qui remplace alors la base YAML comme base de données des écrits.
+static BASE: Mutex<Option<BaseSqlite>> = Mutex::new(None);
+
+/// Erreur de la base SQLite.
+#[derive(Debug)]
+pub enum ErreurSqlite {
+    /// Échec de SQLite lui-même (verrou, entrée-sortie, requête…), conservé comme source de l’erreur.
+    Sqlite(rusqlite::Error),
+    /// Donnée de la base, ou de la base YAML importée, qui ne peut pas être lue comme un écrit.
+    Donnees(ErrType),
+}
+
+impl Display for ErreurSqlite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErreurSqlite::Sqlite(e) => write!(f, "Base SQLite : {e}"),
+            ErreurSqlite::Donnees(e) => write!(f, "Base SQLite : {e}")
+        }
+    }
+}
+
+impl Error for ErreurSqlite {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ErreurSqlite::Sqlite(e) => Some(e),
+            ErreurSqlite::Donnees(_) => None
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ErreurSqlite {
+    fn from(e: rusqlite::Error) -> Self {
+        ErreurSqlite::Sqlite(e)
+    }
+}
+
+impl From<ErrType> for ErreurSqlite {
+    fn from(e: ErrType) -> Self {
+        ErreurSqlite::Donnees(e)
+    }
+}
+
+impl From<InvalidTimestamp> for ErreurSqlite {
+    fn from(e: InvalidTimestamp) -> Self {
+        ErreurSqlite::Donnees(e.into())
+    }
+}
+
+/// Stockage des écrits dans une base SQLite, une table par écrit, marque, tag et entrée d’historique.
+pub struct BaseSqlite {
+    connexion: Connection,
+    /// Écrits tels qu’ils ont été enregistrés pour la dernière fois, pour n’écrire que les changements.
+    enregistres: HashMap<u64, Ecrit>,
+}
+
+impl BaseSqlite {
+    pub fn ouvrir(chemin: &str) -> Result<Self, ErreurSqlite> {
+        let connexion = Connection::open(chemin)?;
+        connexion.execute_batch(SCHEMA)?;
+        let mut base = Self { connexion, enregistres: HashMap::new() };
+        base.enregistres = base.charger()?;
+        Ok(base)
+    }
+
+    pub fn est_vide(&self) -> bool {
+        self.enregistres.is_empty()
+    }
+
+    /// Lit tous les écrits de la base.
+    pub fn charger(&self) -> Result<HashMap<u64, Ecrit>, ErreurSqlite> {
+        let mut ecrits = HashMap::new();
+        let mut requete = self.connexion.prepare(
+            "SELECT id, nom, lien, type, statut, auteur, derniere_maj, salon_discord, flux, membre_auteur FROM ecrits"
+        )?;
+        let lignes = requete.query_map([], |ligne| Ok((
+            ligne.get::<_, i64>(0)?, ligne.get::<_, String>(1)?, ligne.get::<_, String>(2)?, ligne.get::<_, String>(3)?,
+            ligne.get::<_, String>(4)?, ligne.get::<_, String>(5)?, ligne.get::<_, i64>(6)?, ligne.get::<_, Option<i64>>(7)?,
+            ligne.get::<_, Option<String>>(8)?, ligne.get::<_, Option<i64>>(9)?
+        )))?;
+        for ligne in lignes {
+            let (id, nom, lien, type_, statut, auteur, derniere_maj, salon, flux, membre) = ligne?;
+            let id = id as u64;
+            ecrits.insert(id, Ecrit {
+                status: Status::from_str(statut.as_str())?,
+                type_: Type::from_str(type_.as_str())?,
+                nom,
+                lien,
+                last_update: Timestamp::from_unix_timestamp(derniere_maj)?,
+                auteur,
+                interesses: self.charger_marques(id)?,
+                modified: false,
+                tags: self.charger_tags(id)?,
+                discord_chan: salon.map(|salon| ChannelId::new(salon as u64)),
+                flux,
+                historique: self.charger_historique(id)?,
+                membre_auteur: membre.map(|membre| membre as u64),
+                id,
+            });
+        }
+        Ok(ecrits)
+    }
+
+    fn charger_marques(&self, id: u64) -> Result<Vec<Interet>, ErreurSqlite> {
+        let mut requete = self.connexion.prepare(
+            "SELECT nom, membre, date, type, rappel, prolongee FROM marques WHERE ecrit = ?1 ORDER BY date"
+        )?;
+        let lignes = requete.query_map(params![id as i64], |ligne| Ok((
+            ligne.get::<_, String>(0)?, ligne.get::<_, i64>(1)?, ligne.get::<_, i64>(2)?,
+            ligne.get::<_, String>(3)?, ligne.get::<_, Option<i64>>(4)?, ligne.get::<_, Option<i64>>(5)?
+        )))?;
+        lignes.map(|ligne| {
+            let (name, membre, date, type_, rappel, prolongee) = ligne?;
+            Ok(Interet {
+                name,
+                date: Timestamp::from_unix_timestamp(date)?,
+                type_: InteretType::from_cle(type_.as_str())
+                    .ok_or(ErrType::ObjectNotFound(format!("Type de marque {type_} inconnu.")))?,
+                member: membre as u64,
+                rappel: rappel.and_then(|rappel| Timestamp::from_unix_timestamp(rappel).ok()),
+                prolongee: prolongee.and_then(|prolongee| Timestamp::from_unix_timestamp(prolongee).ok()),
+            })
+        }).collect()
+    }
+
+    fn charger_tags(&self, id: u64) -> Result<Vec<String>, ErreurSqlite> {
+        let mut requete = self.connexion.prepare("SELECT tag FROM tags WHERE ecrit = ?1")?;
+        let tags = requete.query_map(params![id as i64], |ligne| ligne.get::<_, String>(0))
+            ?
+            .collect::<Result<Vec<String>, _>>()
+            ?;
+        Ok(tags)
+    }
+
+    fn charger_historique(&self, id: u64) -> Result<Vec<EntreeHistorique>, ErreurSqlite> {
+        let mut requete = self.connexion.prepare(
+            "SELECT date, acteur_id, acteur, action, ancien, nouveau FROM historique WHERE ecrit = ?1 ORDER BY rang"
+        )?;
+        let lignes = requete.query_map(params![id as i64], |ligne| Ok((
+            ligne.get::<_, i64>(0)?, ligne.get::<_, i64>(1)?, ligne.get::<_, Option<String>>(2)?,
+            ligne.get::<_, String>(3)?, ligne.get::<_, Option<String>>(4)?, ligne.get::<_, Option<String>>(5)?
+        )))?;
+        lignes.map(|ligne| {
+            let (date, acteur_id, acteur, action, ancien, nouveau) = ligne?;
+            Ok(EntreeHistorique {
+                date: Timestamp::from_unix_timestamp(date)?,
+                acteur: if acteur_id == 0 {
+                    Acteur::Critibot
+                } else {
+                    Acteur::Membre { id: acteur_id as u64, nom: acteur.unwrap_or_default() }
+                },
+                action: Action::from_cle(action.as_str())
+                    .ok_or(ErrType::ObjectNotFound(format!("Action {action} inconnue.")))?,
+                ancien,
+                nouveau,
+            })
+        }).collect()
+    }
+
+    /// Remplace un écrit et tout ce qui lui est rattaché.
+    fn ecrire(transaction: &Transaction, ecrit: &Ecrit) -> Result<(), rusqlite::Error> {
+        let id = ecrit.id as i64;
+        transaction.execute(
+            "INSERT OR REPLACE INTO ecrits (id, nom, lien, type, statut, auteur, derniere_maj, salon_discord, flux, membre_auteur)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![id, ecrit.nom, ecrit.lien, ecrit.type_.to_string(), ecrit.status.to_string(), ecrit.auteur,
+                    ecrit.last_update.timestamp(), ecrit.discord_chan.map(|salon| salon.get() as i64), ecrit.flux,
+                    ecrit.membre_auteur.map(|membre| membre as i64)]
+        )?;
+        for table in ["marques", "tags", "historique"] {
+            transaction.execute(format!("DELETE FROM {table} WHERE ecrit = ?1").as_str(), params![id])?;
+        }
+        for interet in &ecrit.interesses {
+            transaction.execute(
+                "INSERT INTO marques (ecrit, nom, membre, date, type, rappel, prolongee) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![id, interet.name, interet.member as i64, interet.date.timestamp(), interet.type_.cle(),
+                        interet.rappel.map(|rappel| rappel.timestamp()), interet.prolongee.map(|prolongee| prolongee.timestamp())]
+            )?;
+        }
+        for tag in &ecrit.tags {
+            transaction.execute("INSERT OR IGNORE INTO tags (ecrit, tag) VALUES (?1, ?2)", params![id, tag])?;
+        }
+        for (rang, entree) in ecrit.historique.iter().enumerate() {
+            let (acteur_id, acteur) = match &entree.acteur {
+                Acteur::Membre { id, nom } => (*id as i64, Some(nom.clone())),
+                Acteur::Critibot => (0, None)
+            };
+            transaction.execute(
+                "INSERT INTO historique (ecrit, rang, date, acteur_id, acteur, action, ancien, nouveau)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![id, rang as i64, entree.date.timestamp(), acteur_id, acteur, entree.action.cle(),
+                        entree.ancien, entree.nouveau]
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Enregistre les écrits ajoutés ou modifiés depuis le dernier enregistrement et supprime ceux qui
+    /// ont disparu, chacun dans sa propre transaction. Renvoie le nombre d’écrits écrits ou supprimés.
+    pub fn enregistrer(&mut self, database: &HashMap<u64, Ecrit>) -> Result<usize, ErreurSqlite> {
+        /* L’indicateur de modification du YAML n’est pas stocké et n’est donc pas comparé. */
+        let modifies: Vec<&Ecrit> = database.values()
+            .filter(|ecrit| self.enregistres.get(&ecrit.id)
+                .is_none_or(|enregistre| Ecrit { modified: ecrit.modified, ..enregistre.clone() } != **ecrit))
+            .collect();
+        let supprimes: Vec<u64> = self.enregistres.keys().filter(|id| !database.contains_key(id)).copied().collect();
+
+        /* La copie n’est mise à jour qu’une fois la transaction de l’écrit validée : un écrit dont
+         * l’enregistrement échoue sera de nouveau écrit la fois suivante. */
+        for ecrit in &modifies {
+            let transaction = self.connexion.transaction()?;
+            Self::ecrire(&transaction, ecrit)?;
+            transaction.commit()?;
+            self.enregistres.insert(ecrit.id, (*ecrit).clone());
+        }
+        for id in &supprimes {
+            /* Les marques, tags et entrées d’historique sont supprimés en cascade dans la même instruction. */
+            self.connexion.execute("DELETE FROM ecrits WHERE id = ?1", params![*id as i64])?;
+            self.enregistres.remove(id);
+        }
+        Ok(modifies.len() + supprimes.len())
+    }
+
+    /// Importe en une seule transaction tous les écrits lisibles d’une base de données YAML. Renvoie le
+    /// nombre d’écrits importés.
+    pub fn importer_yaml(&mut self, chemin: &str) -> Result<usize, ErreurSqlite> {
+        let contenu = fs::read_to_string(chemin)
+            .map_err(|e| ErrType::YamlParseError(format!("Impossible de lire {chemin} : {e}")))?;
+        let docs = YamlLoader::load_from_str(contenu.as_str())
+            .map_err(|e| ErrType::YamlParseError(format!("Base de données {chemin} illisible : {e}")))?;
+        let mut ecrits = HashMap::new();
+        docs.iter().for_each(|doc| lire_ecrits(doc, &mut ecrits));
+
+        let transaction = self.connexion.transaction()?;
+        for ecrit in ecrits.values() {
+            Self::ecrire(&transaction, ecrit)?;
+        }
+        transaction.commit()?;
+        let nombre = ecrits.len();
+        self.enregistres.extend(ecrits);
+        Ok(nombre)
+    }
+}
+
+/// Ouvre la base SQLite, qui devient la base de données des écrits. Une base vide reçoit une fois pour
+/// toutes les écrits de la base YAML `chemin_yaml`, qui n’est ensuite plus lue. Renvoie le nombre
+/// d’écrits importés.
+pub fn ouvrir(chemin: &str, chemin_yaml: &str) -> Result<usize, ErreurSqlite> {
+    let mut base = BaseSqlite::ouvrir(chemin)?;
+    let importes = if base.est_vide() && std::path::Path::new(chemin_yaml).exists() {
+        base.importer_yaml(chemin_yaml)?
+    } else {
+        0
+    };
+    *BASE.lock().unwrap() = Some(base);
+    Ok(importes)
+}
+
+/// Cherche récursivement les écrits d’un document YAML de la base de données.
+fn lire_ecrits(yaml: &Yaml, ecrits: &mut HashMap<u64, Ecrit>) {
+    let elements: Vec<&Yaml> = match yaml {
+        Yaml::Array(elements) => elements.iter().collect(),
+        Yaml::Hash(hash) => hash.values().collect(),
+        _ => return
+    };
+    for element in elements {
+        match Ecrit::from_yaml(element) {
+            Ok(ecrit) => {
+                ecrits.insert(ecrit.get_id(), ecrit);
+            }
+            Err(_) => lire_ecrits(element, ecrits)
+        }
+    }
+}
+
+impl Ecrit {
+    /// Remplace les écrits que la bibliothèque a chargés depuis la base YAML par ceux de la base SQLite.
+    pub fn charger_sqlite(bot: &mut Bot<Self>) {
+        if let Some(base) = BASE.lock().unwrap().as_ref() {
+            bot.database = base.enregistres.clone();
+            bot.update_affichans = true;
+        }
+    }
+
+    /// Écrit dans la base SQLite les changements faits depuis le dernier enregistrement.
+    pub fn enregistrer_sqlite(database: &HashMap<u64, Self>) {
+        if let Some(base) = BASE.lock().unwrap().as_mut() {
+            if let Err(e) = base.enregistrer(database) {
+                eprintln!("Erreur lors de l’enregistrement dans la base SQLite : {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ecrit(id: u64, nom: &str) -> Ecrit {
+        let mut ecrit = Ecrit::new(nom.to_string(), format!("http://fondationscp.wikidot.com/forum/t-{id}/"),
+                                   Type::Conte, Status::Ouvert, "Auteur".to_string()).unwrap();
+        ecrit.tags = vec!["Humanoïde".to_string()];
+        ecrit.historiser(&Acteur::Critibot, Action::Ajout, None, None);
+        ecrit
+    }
+
+    #[test]
+    fn erreur_sqlite_conservee() {
+        let erreur = BaseSqlite::ouvrir("/dossier/inexistant/critibot.db").err().unwrap();
+        assert!(matches!(erreur, ErreurSqlite::Sqlite(_)));
+        assert!(erreur.source().is_some());
+    }
+
+    #[test]
+    fn changements_enregistres_et_relus() {
+        let mut base = BaseSqlite::ouvrir(":memory:").unwrap();
+        let mut database = HashMap::from([(1, ecrit(1, "Premier")), (2, ecrit(2, "Second"))]);
+        assert_eq!(base.enregistrer(&database).unwrap(), 2);
+        assert_eq!(base.enregistrer(&database).unwrap(), 0);
+
+        database.get_mut(&1).unwrap().status = Status::EnAttente;
+        database.get_mut(&1).unwrap().membre_auteur = Some(42);
+        database.remove(&2);
+        assert_eq!(base.enregistrer(&database).unwrap(), 2);
+
+        let relus = base.charger().unwrap();
+        assert_eq!(relus.len(), 1);
+        let relu = &relus[&1];
+        assert_eq!((&relu.nom, &relu.status, &relu.tags), (&database[&1].nom, &Status::EnAttente, &database[&1].tags));
+        assert_eq!(relu.last_update.timestamp(), database[&1].last_update.timestamp());
+        assert_eq!(relu.historique.iter().map(|entree| &entree.action).collect::<Vec<_>>(), [&Action::Ajout]);
+        assert_eq!(relu.membre_auteur, Some(42));
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use chrono::{TimeDelta, Utc};
+use fondabots_lib::yaml_rust2::{yaml, Yaml, YamlEmitter, YamlLoader};
+use fondabots_lib::ErrType;
+use poise::{serenity_prelude as serenity, ChoiceParameter};
+use serenity::all::Timestamp;
+
+use super::Ecrit;
+
+/// Journal des événements de critique. Il est séparé de la base de données pour que les statistiques
+/// survivent au nettoyage des écrits publiés ou refusés.
+const CHEMIN_STATISTIQUES: &str = "./critibot.stats.yml";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TypeEvenement {
+    Marque,
+    Critique,
+    /// Marque retirée sans que son auteur ait critiqué l’écrit.
+    Liberation,
+}
+
+impl TypeEvenement {
+    pub fn cle(&self) -> &'static str {
+        match self {
+            TypeEvenement::Marque => "marque",
+            TypeEvenement::Critique => "critique",
+            TypeEvenement::Liberation => "liberation",
+        }
+    }
+
+    pub fn from_cle(cle: &str) -> Option<Self> {
+        [TypeEvenement::Marque, TypeEvenement::Critique, TypeEvenement::Liberation].into_iter()
+            .find(|type_| type_.cle() == cle)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Evenement {
+    pub date: Timestamp,
+    pub membre: u64,
+    pub nom: String,
+    pub type_: TypeEvenement,
+    pub ecrit: u64,
+    /// Pour une critique, temps écoulé depuis la marque du critique, en secondes.
+    pub delai: Option<i64>,
+}
+
+impl Evenement {
+    /// Événement causé par l’action que vient d’enregistrer l’historique de l’écrit. Il en prend la date,
+    /// qui permet de le retirer du journal si l’action est annulée.
+    pub fn nouveau(ecrit: &Ecrit, membre: u64, nom: &str, type_: TypeEvenement, delai: Option<i64>) -> Self {
+        Self {
+            date: ecrit.historique.last().map_or_else(Timestamp::now, |entree| entree.date),
+            membre,
+            nom: nom.to_string(),
+            type_,
+            ecrit: ecrit.id,
+            delai,
+        }
+    }
+
+    fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        let erreur = |champ: &str| ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ} des statistiques."));
+        Ok(Self {
+            date: Timestamp::from_unix_timestamp(data["date"].as_i64().ok_or(erreur("date"))?)?,
+            membre: data["membre"].as_i64().ok_or(erreur("membre"))?.unsigned_abs(),
+            nom: data["nom"].as_str().ok_or(erreur("nom"))?.to_string(),
+            type_: TypeEvenement::from_cle(data["type"].as_str().ok_or(erreur("type"))?).ok_or(erreur("type"))?,
+            ecrit: data["ecrit"].as_i64().ok_or(erreur("ecrit"))?.unsigned_abs(),
+            delai: data["delai"].as_i64(),
+        })
+    }
+
+    fn serialize(&self) -> Yaml {
+        let mut hash = yaml::Hash::new();
+        hash.insert(Yaml::String("date".to_string()), Yaml::Integer(self.date.timestamp()));
+        hash.insert(Yaml::String("membre".to_string()), Yaml::Integer(self.membre as i64));
+        hash.insert(Yaml::String("nom".to_string()), Yaml::String(self.nom.clone()));
+        hash.insert(Yaml::String("type".to_string()), Yaml::String(self.type_.cle().to_string()));
+        hash.insert(Yaml::String("ecrit".to_string()), Yaml::Integer(self.ecrit as i64));
+        if let Some(delai) = self.delai {
+            hash.insert(Yaml::String("delai".to_string()), Yaml::Integer(delai));
+        }
+        Yaml::Hash(hash)
+    }
+}
+
+static JOURNAL: Mutex<Vec<Evenement>> = Mutex::new(Vec::new());
+
+/// Nombre d’événements lus au démarrage, en tête du journal. Seuls les événements suivants, enregistrés
+/// depuis le démarrage, peuvent appartenir à une action annulée.
+static EVENEMENTS_CHARGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Charge le journal des statistiques. Un journal absent est un journal vide.
+pub fn charger() -> Result<(), ErrType> {
+    let contenu = match fs::read_to_string(CHEMIN_STATISTIQUES) {
+        Ok(contenu) => contenu,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ErrType::YamlParseError(format!("{CHEMIN_STATISTIQUES} : {e}")))
+    };
+    let docs = YamlLoader::load_from_str(contenu.as_str())
+        .map_err(|e| ErrType::YamlParseError(format!("{CHEMIN_STATISTIQUES} illisible : {e}")))?;
+    let evenements = match docs.first() {
+        Some(Yaml::Array(evenements)) => evenements.iter().map(Evenement::from_yaml).collect::<Result<Vec<_>, _>>()?,
+        _ => Vec::new()
+    };
+    EVENEMENTS_CHARGES.store(evenements.len(), Ordering::Relaxed);
+    *JOURNAL.lock().unwrap() = evenements;
+    Ok(())
+}
+
+fn emettre(evenements: &[Evenement]) -> Result<String, String> {
+    let mut sortie = String::new();
+    YamlEmitter::new(&mut sortie).dump(&Yaml::Array(evenements.iter().map(Evenement::serialize).collect()))
+        .map_err(|e| e.to_string())?;
+    Ok(sortie)
+}
+
+/// Ajoute au journal les événements d’une action, une fois celle-ci effectuée. Les marques par
+/// procuration, sans identifiant de membre, ne sont pas comptées.
+pub fn enregistrer(evenements: Vec<Evenement>) {
+    let evenements: Vec<Evenement> = evenements.into_iter().filter(|evenement| evenement.membre != 0).collect();
+    if evenements.is_empty() {
+        return;
+    }
+    /* Le journal est une liste yaml : chaque événement y est ajouté comme un élément de plus. */
+    let ecriture = emettre(&evenements)
+        .and_then(|sortie| OpenOptions::new().create(true).append(true).open(CHEMIN_STATISTIQUES)
+            .and_then(|mut fichier| writeln!(fichier, "{}", sortie.trim_start_matches("---").trim_start()))
+            .map_err(|e| e.to_string()));
+    if let Err(e) = ecriture {
+        eprintln!("Impossible d’enregistrer un événement dans {CHEMIN_STATISTIQUES} : {e}");
+    }
+    JOURNAL.lock().unwrap().extend(evenements);
+}
+
+/// L’action de l’événement a été annulée : l’écrit, remis dans un état antérieur, ne l’a plus dans
+/// son historique. Les dates sont comparées à la seconde, la précision de la base enregistrée.
+fn annule(evenement: &Evenement, ecrits: &HashMap<u64, &Ecrit>) -> bool {
+    ecrits.get(&evenement.ecrit)
+        .is_some_and(|ecrit| !ecrit.historique.iter().any(|entree| entree.date.timestamp() == evenement.date.timestamp()))
+}
+
+/// Retire du journal les événements des actions annulées sur les écrits donnés, remis dans un état
+/// antérieur : ceux enregistrés depuis le démarrage dont l’action n’est plus dans l’historique de l’écrit.
+pub fn retirer_annules<'a>(ecrits: impl IntoIterator<Item = &'a Ecrit>) {
+    let ecrits: HashMap<u64, &Ecrit> = ecrits.into_iter().map(|ecrit| (ecrit.id, ecrit)).collect();
+    let mut journal = JOURNAL.lock().unwrap();
+    let charges = EVENEMENTS_CHARGES.load(Ordering::Relaxed).min(journal.len());
+    let recents = journal.split_off(charges);
+    let nombre = recents.len();
+    journal.extend(recents.into_iter().filter(|evenement| !annule(evenement, &ecrits)));
+    if journal.len() == charges + nombre {
+        return;
+    }
+    let ecriture = emettre(&journal)
+        .and_then(|sortie| fs::write(CHEMIN_STATISTIQUES, sortie + "\n").map_err(|e| e.to_string()));
+    if let Err(e) = ecriture {
+        eprintln!("Impossible de réécrire {CHEMIN_STATISTIQUES} : {e}");
+    }
+}
+
+/// Période couverte par les statistiques.
+#[derive(ChoiceParameter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Periode {
+    #[name = "7 derniers jours"]
+    Semaine,
+    #[name = "30 derniers jours"]
+    Mois,
+    #[name = "365 derniers jours"]
+    Annee,
+    #[name = "Depuis toujours"]
+    Tout,
+}
+
+impl Periode {
+    fn debut(&self) -> Option<Timestamp> {
+        let jours = match self {
+            Periode::Semaine => 7,
+            Periode::Mois => 30,
+            Periode::Annee => 365,
+            Periode::Tout => return None,
+        };
+        Some(Timestamp::from(Utc::now() - TimeDelta::days(jours)))
+    }
+}
+
+/// Compteurs d’un membre sur une période.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct StatistiquesMembre {
+    /// Dernier nom connu du membre.
+    pub nom: String,
+    pub critiques: usize,
+    pub marques: usize,
+    pub liberations: usize,
+    delais: Vec<i64>,
+}
+
+impl StatistiquesMembre {
+    /// Temps moyen entre la marque d’un écrit et sa critique.
+    pub fn delai_moyen(&self) -> Option<TimeDelta> {
+        if self.delais.is_empty() {
+            None
+        } else {
+            Some(TimeDelta::seconds(self.delais.iter().sum::<i64>() / self.delais.len() as i64))
+        }
+    }
+}
+
+/// Statistiques de chaque membre ayant eu une activité sur la période.
+pub fn calculer(periode: Periode) -> HashMap<u64, StatistiquesMembre> {
+    let debut = periode.debut();
+    let mut statistiques: HashMap<u64, StatistiquesMembre> = HashMap::new();
+    for evenement in JOURNAL.lock().unwrap().iter().filter(|evenement| debut.is_none_or(|debut| evenement.date >= debut)) {
+        let membre = statistiques.entry(evenement.membre).or_default();
+        membre.nom = evenement.nom.clone();
+        match evenement.type_ {
+            TypeEvenement::Marque => membre.marques += 1,
+            TypeEvenement::Liberation => membre.liberations += 1,
+            TypeEvenement::Critique => {
+                membre.critiques += 1;
+                membre.delais.extend(evenement.delai);
+            }
+        }
+    }
+    statistiques
+}
+
+/// Affiche une durée en jours et heures, ou en heures et minutes si elle est courte.
+pub fn afficher_duree(duree: TimeDelta) -> String {
+    if duree.num_days() > 0 {
+        format!("{} j {} h", duree.num_days(), duree.num_hours() % 24)
+    } else {
+        format!("{} h {} min", duree.num_hours(), duree.num_minutes() % 60)
+    }
+}
+
+/// Événements d’un type survenus entre deux dates, du plus ancien au plus récent.
+pub fn evenements(type_: TypeEvenement, debut: Timestamp, fin: Timestamp) -> Vec<Evenement> {
+    JOURNAL.lock().unwrap().iter()
+        .filter(|evenement| evenement.type_ == type_ && evenement.date >= debut && evenement.date < fin)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecrit::fields::{Interet, InteretType, Status, Type};
+    use crate::ecrit::historique::Acteur;
+
+    fn ecrit_marque() -> Ecrit {
+        let mut ecrit = Ecrit::new("Le Mur".to_string(), "http://fondationscp.wikidot.com/forum/t-1/".to_string(),
+                                   Type::Rapport, Status::Ouvert, "Dr Foo".to_string()).unwrap();
+        for (membre, nom) in [(1, "Alice"), (2, "Bob")] {
+            ecrit.interesses.push(Interet {
+                name: nom.to_string(),
+                date: Timestamp::now(),
+                type_: InteretType::Simple,
+                member: membre,
+                rappel: None,
+                prolongee: None,
+            });
+        }
+        ecrit
+    }
+
+    #[test]
+    fn critique_et_liberations() {
+        let mut ecrit = ecrit_marque();
+        let evenements = ecrit.critique(&Acteur::Membre { id: 1, nom: "Alice".to_string() });
+        let types: Vec<(u64, TypeEvenement)> = evenements.iter().map(|evenement| (evenement.membre, evenement.type_)).collect();
+        assert_eq!(types, vec![(2, TypeEvenement::Liberation), (1, TypeEvenement::Critique)]);
+        assert!(evenements[1].delai.is_some());
+        assert!(evenements.iter().all(|evenement| evenement.ecrit == ecrit.id));
+    }
+
+    #[test]
+    fn evenements_retires_quand_l_action_est_annulee() {
+        let avant = ecrit_marque();
+        let mut apres = avant.clone();
+        let evenements = apres.critique(&Acteur::Membre { id: 1, nom: "Alice".to_string() });
+
+        let ecrits = HashMap::from([(apres.id, &apres)]);
+        assert!(evenements.iter().all(|evenement| !annule(evenement, &ecrits)));
+
+        /* Annuler remet l’écrit dans son état archivé, sans l’entrée de la critique. */
+        let ecrits = HashMap::from([(avant.id, &avant)]);
+        assert!(evenements.iter().all(|evenement| annule(evenement, &ecrits)));
+
+        /* Les événements des autres écrits ne sont pas concernés. */
+        assert!(evenements.iter().all(|evenement| !annule(evenement, &HashMap::new())));
+    }
+
+    #[test]
+    fn liberation() {
+        let mut ecrit = ecrit_marque();
+        let evenement = ecrit.liberer_id(2, &Acteur::Critibot).unwrap();
+        assert_eq!((evenement.membre, evenement.type_), (2, TypeEvenement::Liberation));
+        assert_eq!(evenement.date, ecrit.historique.last().unwrap().date);
+        assert!(ecrit.liberer_id(2, &Acteur::Critibot).is_none());
+    }
+}
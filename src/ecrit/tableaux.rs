@@ -0,0 +1,93 @@
+use chrono::Utc;
+use fondabots_lib::{tools, Bot, ErrType};
+use poise::serenity_prelude as serenity;
+use serenity::all::{ButtonStyle, ComponentInteraction, Context as SerenityContext, CreateActionRow, CreateButton,
+                    CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponse,
+                    CreateInteractionResponseMessage, Timestamp};
+
+use crate::config;
+use super::historique::Acteur;
+use super::statistiques::{self, afficher_duree};
+use super::{Ecrit, ModeTags};
+
+/// Nombre de marques par page : chacune a sa ligne de boutons, la dernière ligne sert aux pages.
+const MARQUES_PAR_PAGE: usize = 4;
+
+impl Ecrit {
+    /// Page du tableau des marques d’un membre : la liste de ses marques, de la plus ancienne à la plus
+    /// récente, avec pour chacune un bouton pour la libérer et un pour marquer l’écrit comme critiqué.
+    pub fn tableau_marques(bot: &Bot<Self>, membre: u64, page: usize) -> (CreateEmbed, Vec<CreateActionRow>) {
+        let mut marques: Vec<_> = Self::ulister(bot, String::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
+                                                ModeTags::Un, None, None, Some(membre)).into_iter()
+            .filter_map(|id| bot.database.get(id))
+            .filter_map(|ecrit| ecrit.interesses.iter().find(|interet| interet.member == membre).map(|interet| (ecrit, interet)))
+            .collect();
+        marques.sort_by_key(|(_, interet)| interet.date);
+
+        let pages = marques.len().div_ceil(MARQUES_PAR_PAGE).max(1);
+        let page = page.min(pages - 1);
+        let debut = page * MARQUES_PAR_PAGE;
+        let marques_page = &marques[debut.min(marques.len())..(debut + MARQUES_PAR_PAGE).min(marques.len())];
+
+        let embed = CreateEmbed::new()
+            .author(CreateEmbedAuthor::new("Mes marques"))
+            .title(format!("{} marque(s) en cours", marques.len()))
+            .description(if marques_page.is_empty() {
+                "Vous n’avez marqué aucun écrit.".to_string()
+            } else {
+                marques_page.iter().enumerate().map(|(rang, (ecrit, interet))| {
+                    let expiration = config::get().marques.delais(&interet.type_)
+                        .map(|delais| format!(", retirée le {}", (*interet.debut_delais() + delais.expiration).format("%d/%m/%Y")))
+                        .unwrap_or_default();
+                    format!("**{}.** [{}]({}) ({}) — {}, posée il y a {}{expiration}\n", debut + rang + 1, ecrit.nom, ecrit.lien,
+                            ecrit.status, interet.type_, afficher_duree(Utc::now() - *interet.date))
+                }).collect::<String>()
+            })
+            .footer(CreateEmbedFooter::new(format!("Page {}/{pages}", page + 1)))
+            .color(73887)
+            .timestamp(Timestamp::now());
+
+        let mut composants: Vec<CreateActionRow> = marques_page.iter().enumerate().map(|(rang, (ecrit, _))| CreateActionRow::Buttons(vec![
+            CreateButton::new(format!("ml-{}-{page}", ecrit.id)).label(format!("{}. Libérer", debut + rang + 1)).style(ButtonStyle::Secondary),
+            /* Même bouton que sous l’embed de l’écrit, avec sa confirmation et ses vérifications. */
+            CreateButton::new(format!("e-{}-c", ecrit.id)).label(format!("{}. Critiqué", debut + rang + 1)).style(ButtonStyle::Success),
+        ])).collect();
+        if pages > 1 {
+            composants.push(CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("mm-{}", page.saturating_sub(1))).label("Précédent").style(ButtonStyle::Secondary).disabled(page == 0),
+                CreateButton::new(format!("mm-{}", page + 1)).label("Suivant").style(ButtonStyle::Secondary).disabled(page + 1 >= pages),
+            ]));
+        }
+        (embed, composants)
+    }
+
+    /// Boutons du tableau des marques : changement de page (`mm-{page}`) et libération d’une marque
+    /// (`ml-{écrit}-{page}`). Le tableau est celui du membre qui clique.
+    pub(super) async fn composant_tableau_marques(ctx: &SerenityContext, interaction: &ComponentInteraction, bot: &mut Bot<Self>,
+                                                  type_: &str, parts: &[&str]) -> Result<(), ErrType> {
+        let erreur_id = || ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get());
+        let membre = interaction.user.id.get();
+        let page: usize = match type_ {
+            "ml" => {
+                let id: u64 = parts.get(1).ok_or_else(erreur_id)?.parse()?;
+                let page = parts.get(2).ok_or_else(erreur_id)?.parse()?;
+                if bot.database.get(&id).is_some_and(|ecrit| ecrit.interesses.iter().any(|interet| interet.member == membre)) {
+                    bot.archive(vec![id]);
+                    let ecrit = bot.database.get_mut(&id).unwrap();
+                    let liberation = ecrit.liberer_id(membre, &Acteur::from(&interaction.user));
+                    let nom = ecrit.nom.clone();
+                    bot.log(ctx, format!("{} a libéré sa marque sur l'écrit {nom} (id: {id}).", tools::user_desc(&interaction.user))).await?;
+                    bot.update_affichans(ctx).await?;
+                    Self::sauvegarder(bot)?;
+                    statistiques::enregistrer(liberation.into_iter().collect());
+                }
+                page
+            }
+            _ => parts.get(1).ok_or_else(erreur_id)?.parse()?
+        };
+        let (embed, composants) = Self::tableau_marques(bot, membre, page);
+        interaction.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().embed(embed).components(composants))).await?;
+        Ok(())
+    }
+}
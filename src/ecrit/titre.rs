@@ -0,0 +1,159 @@
+use fondabots_lib::ErrType;
+use regex::Regex;
+
+use super::fields::Type;
+
+/// Associe des mots-clés trouvés dans les balises entre crochets d’un titre à un type d’écrit.
+#[derive(Clone, Debug)]
+pub struct RegleBalise {
+    pub mots: Vec<String>,
+    pub type_: Type,
+}
+
+impl RegleBalise {
+    pub fn new(mots: &[&str], type_: Type) -> Self {
+        Self {
+            mots: mots.iter().map(|mot| mot.to_lowercase()).collect(),
+            type_,
+        }
+    }
+
+    fn correspond(&self, balise: &str) -> bool {
+        self.mots.iter().any(|mot| balise.contains(mot.as_str()))
+    }
+
+    /// Règles utilisées lorsque la configuration n’en donne pas. L’ordre compte : la première règle
+    /// correspondant à une balise l’emporte.
+    pub fn par_defaut() -> Vec<Self> {
+        vec![
+            Self::new(&["idée", "idee"], Type::Idee),
+            Self::new(&["conte", "série", "serie"], Type::Conte),
+            Self::new(&["format"], Type::FormatGdi),
+        ]
+    }
+}
+
+/// Résultat de l’analyse d’un titre de fil du forum des critiques.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Titre {
+    /// Nom de l’écrit, débarrassé des balises, du numéro SCP et de la mention de version.
+    pub nom: String,
+    /// Type deviné à partir des balises, `None` si aucune balise ne correspond à une règle.
+    pub type_: Option<Type>,
+    /// Numéro SCP tel qu’écrit dans le titre (ex. « 1234-FR »).
+    pub numero_scp: Option<String>,
+    /// Le titre indique une version provisoire ou temporaire.
+    pub provisoire: bool,
+    /// Balises entre crochets qui ne désignent pas de type, dans l’ordre du titre.
+    pub balises: Vec<String>,
+}
+
+impl Titre {
+    /// Tags à donner à l’écrit : les balises qui ne désignent pas de type, le numéro SCP et la
+    /// mention de version provisoire.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        let extras = self.numero_scp.iter().map(|numero| format!("SCP-{numero}"))
+            .chain(self.provisoire.then(|| "Provisoire".to_string()));
+        for tag in self.balises.iter().cloned().chain(extras) {
+            if !tag.is_empty() && !tags.iter().any(|connu| connu.to_lowercase() == tag.to_lowercase()) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+}
+
+/// Analyseur des titres du forum, à construire une fois par mise à jour pour ne compiler les
+/// expressions régulières qu’une seule fois.
+pub struct AnalyseurTitre {
+    regles: Vec<RegleBalise>,
+    regex_balises: Regex,
+    regex_titres: Regex,
+    regex_scp: Regex,
+    regex_provisoire: Regex,
+}
+
+impl AnalyseurTitre {
+    pub fn new(regles: Vec<RegleBalise>) -> Result<Self, ErrType> {
+        Ok(Self {
+            regles,
+            regex_balises: Regex::new(r##"\s*\[([^\[]*)]"##)?,
+            /* OH FUCK */
+            regex_titres: Regex::new(r##"(?i)\s*(?:\s*[\[(][^\[]*?[])][\s/\\\-]*)*(?:scp(?:[-\s][\dXY#█?]+(?:[-\s]fr)?)?)?[\s:\-"]*([^"]*?(?:"[^"]+"?[^"]*?)*)[\s".]*(?:\(.*(?:provisoire|temporaire|version).*\))?[\s".]*$"##)?,
+            regex_scp: Regex::new(r##"(?i)scp[-\s]([\dXY#█?]+(?:[-\s]fr)?)"##)?,
+            regex_provisoire: Regex::new(r##"(?i)\(.*(?:provisoire|temporaire|version).*\)"##)?,
+        })
+    }
+
+    /// Analyse un titre. Renvoie `None` si aucun nom n’a pu en être extrait.
+    pub fn analyser(&self, titre: &str) -> Option<Titre> {
+        let nom = self.regex_titres.captures(titre)
+            .and_then(|capture| capture.extract::<1>().1.to_vec().pop())
+            .map(|nom| nom.to_string())?;
+
+        let (balises_type, balises): (Vec<(String, Option<Type>)>, Vec<_>) = self.regex_balises.captures_iter(titre)
+            .map(|balise| balise.extract::<1>().1[0].trim().to_string())
+            .map(|balise| {
+                let type_ = self.regles.iter().find(|regle| regle.correspond(balise.to_lowercase().as_str()))
+                    .map(|regle| regle.type_.clone());
+                (balise, type_)
+            })
+            .partition(|(_, type_)| type_.is_some());
+
+        /* La dernière balise reconnue l’emporte, comme avec l’ancienne détection. */
+        let type_ = balises_type.into_iter().filter_map(|(_, type_)| type_).next_back();
+        let balises = balises.into_iter().map(|(balise, _)| balise).collect();
+
+        Some(Titre {
+            nom,
+            type_,
+            numero_scp: self.regex_scp.captures(titre)
+                .map(|capture| capture.extract::<1>().1[0].to_uppercase()),
+            provisoire: self.regex_provisoire.is_match(titre),
+            balises,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Titres de fils réels du forum des critiques, avec le résultat attendu de leur analyse.
+    const CORPUS: &str = include_str!("../../tests/fixtures/titres.tsv");
+
+    fn colonne_optionnelle(colonne: &str) -> Option<&str> {
+        (colonne != "-").then_some(colonne)
+    }
+
+    #[test]
+    fn corpus_de_titres() {
+        let analyseur = AnalyseurTitre::new(RegleBalise::par_defaut()).unwrap();
+        let lignes: Vec<&str> = CORPUS.lines().filter(|ligne| !ligne.starts_with('#') && !ligne.trim().is_empty()).collect();
+        assert!(!lignes.is_empty());
+        for ligne in lignes {
+            let colonnes: Vec<&str> = ligne.split('\t').collect();
+            let [titre, nom, type_, numero, provisoire, tags] = colonnes[..] else {
+                panic!("Ligne du corpus malformée : {ligne}");
+            };
+            let resultat = analyseur.analyser(titre).unwrap_or_else(|| panic!("Aucun nom extrait de « {titre} »"));
+            assert_eq!(resultat.nom, nom, "nom de « {titre} »");
+            assert_eq!(resultat.type_.as_ref().map(Type::to_string).as_deref(), colonne_optionnelle(type_), "type de « {titre} »");
+            assert_eq!(resultat.numero_scp.as_deref(), colonne_optionnelle(numero), "numéro de « {titre} »");
+            assert_eq!(resultat.provisoire, provisoire == "oui", "version provisoire de « {titre} »");
+            let tags_attendus: Vec<&str> = colonne_optionnelle(tags).map(|tags| tags.split(',').collect()).unwrap_or_default();
+            assert_eq!(resultat.tags(), tags_attendus, "tags de « {titre} »");
+        }
+    }
+
+    #[test]
+    fn derniere_balise_reconnue_l_emporte() {
+        let analyseur = AnalyseurTitre::new(vec![
+            RegleBalise::new(&["conte"], Type::Conte),
+            RegleBalise::new(&["idée"], Type::Idee),
+        ]).unwrap();
+        assert_eq!(analyseur.analyser("[Idée] [Conte] Titre").unwrap().type_, Some(Type::Conte));
+        assert_eq!(analyseur.analyser("[Conte] [Idée] Titre").unwrap().type_, Some(Type::Idee));
+    }
+}
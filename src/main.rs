@@ -1,37 +1,49 @@
 use std::collections::{HashMap, HashSet};
 use std::env;
+use std::sync::OnceLock;
 
-use fondabots_lib::{affichan::Affichan, Bot, ErrType, Object};
+use fondabots_lib::{affichan::Affichan, Bot, ErrType};
 use poise::futures_util::FutureExt;
 use poise::serenity_prelude as serenity;
 use poise::{BoxFuture, Context};
-use serenity::all::{ChannelId, FullEvent, GatewayIntents, GuildChannel, ReactionType, RoleId, UserId};
+use serenity::all::{FullEvent, GatewayIntents, GuildChannel, Interaction, ReactionType};
 
 use ecrit::{
     fields::Status,
     fields::Type,
+    historique::{Acteur, Action},
     Ecrit
 };
-use fondabots_lib::command_data::{CommandData, Permission};
+use fondabots_lib::command_data::CommandData;
 use regex::Regex;
 
+mod autocompletion;
+mod config;
 mod ecrit;
 mod commands;
+mod permissions;
 pub type DataType = fondabots_lib::DataType<Ecrit>;
 
+/// Contexte Discord conservé à la connexion, pour les traitements qui ne sont déclenchés ni par une
+/// commande ni par une interaction (journalisation depuis la mise à jour RSS, par exemple).
+static CONTEXTE: OnceLock<serenity::Context> = OnceLock::new();
+
+/// Résumé des migrations et de la mise en quarantaine des écrits au démarrage, journalisé à la connexion.
+static RESUME_CHARGEMENT: OnceLock<String> = OnceLock::new();
+
+/// Base de données des écrits.
+pub const CHEMIN_BASE: &str = "./critibot.yml";
+
+pub fn contexte() -> Option<&'static serenity::Context> {
+    CONTEXTE.get()
+}
+
 fn command_checker(ctx: Context<'_, DataType, ErrType>) -> BoxFuture<Result<bool, ErrType>> {
     async move {
         let permissions = ctx.command().custom_data.downcast_ref().unwrap_or(&CommandData::default()).permission;
         let member = ctx.author_member().await;
         let auth = match member {
-            Some(member) => {
-                let can_thanks_to_perms = match permissions {
-                    Permission::READ | Permission::NONE => true,
-                    Permission::WRITE => member.roles.contains(&RoleId::new(417334522775076864)), /* Classe-C membre */
-                    Permission::MANAGE => member.roles.contains(&RoleId::new(811582204790571020)) /* Équipe Critique */
-                };
-                can_thanks_to_perms || member.roles.contains(&RoleId::new(417333090625781761)) /* Staff */
-            },
+            Some(member) => permissions::accorde(&member.roles, permissions),
             None => false
         };
         if !auth {
@@ -43,48 +55,133 @@ fn command_checker(ctx: Context<'_, DataType, ErrType>) -> BoxFuture<Result<bool
 
 fn event_handler<'a>(ctx: &'a serenity::Context, event: &'a FullEvent, data: &'a DataType) -> BoxFuture<'a, Result<bool, ErrType>> {
     async move {
-        match event {
+        let resultat = match event {
             FullEvent::ThreadCreate { thread  } => thread_created(ctx, data, thread).await,
+            FullEvent::Ready { .. } => {
+                /* Ready est de nouveau reçu à chaque reconnexion : la surveillance n’est lancée qu’une fois. */
+                if CONTEXTE.set(ctx.clone()).is_ok() {
+                    tokio::spawn(Ecrit::surveiller_marques(ctx.clone(), data.clone()));
+                    tokio::spawn(Ecrit::publier_bilans(ctx.clone(), data.clone()));
+                    tokio::spawn(Ecrit::surveiller_archivage(ctx.clone(), data.clone()));
+                    #[cfg(feature = "sqlite")]
+                    Ecrit::charger_sqlite(&mut *data.lock().await);
+                    /* Les écrits enregistrés avant le registre des auteurs reçoivent le membre de leur auteur. */
+                    Ecrit::resoudre_auteurs(&mut *data.lock().await);
+                    if let Some(resume) = RESUME_CHARGEMENT.get() {
+                        data.lock().await.log(ctx, resume.clone()).await?;
+                    }
+                }
+                Ok(true)
+            }
+            FullEvent::InteractionCreate { interaction: Interaction::Command(commande) } if commande.data.name == "annuler" => {
+                /* La commande de la bibliothèque vient de restaurer des écrits archivés. */
+                ecrit::statistiques::retirer_annules(data.lock().await.database.values());
+                Ok(true)
+            }
             _ => Ok(true)
+        };
+        /* Poise n’appelle ce gestionnaire qu’une fois la commande de l’interaction exécutée : la base
+         * enregistrée par la bibliothèque reçoit de nouveau la version de son format. L’autocomplétion
+         * ne modifie pas la base. */
+        if matches!(event, FullEvent::InteractionCreate { interaction: Interaction::Command(_) | Interaction::Component(_) }
+                         | FullEvent::ThreadCreate { .. }) {
+            if let Err(e) = ecrit::migrations::inscrire_version(CHEMIN_BASE) {
+                eprintln!("Impossible d’inscrire la version de la base de données : {e}");
+            }
+            #[cfg(feature = "sqlite")]
+            Ecrit::enregistrer_sqlite(&data.lock().await.database);
         }
+        resultat
     }.boxed()
 }
 
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = env::args().collect();
+    let config = match config::Config::load() {
+        Ok(config) => config,
+        Err(e) => panic!("Erreur lors du chargement de la configuration: {e}")
+    };
     let mut owners = HashSet::new();
-    owners.insert(UserId::new(340877529973784586));
+    owners.insert(config.proprietaire);
+    let mut affichans = vec![
+        Affichan::new(config.affichans.ouverts, Box::new(|ecrit: &Ecrit| {
+            ecrit.status == Status::Ouvert || ecrit.status == Status::OuvertPlus
+        })),
+        Affichan::new(config.affichans.inconnus, Box::new(|ecrit: &Ecrit| {
+            ecrit.status == Status::Inconnu || ecrit.status == Status::Infraction
+        })),
+        Affichan::new(config.affichans.autres, Box::new(|ecrit: &Ecrit| {
+            ecrit.type_ == Type::Autre
+        })),
+    ];
+    config.flux.iter().for_each(|flux| if let Some(affichan) = flux.affichan {
+        let nom_flux = flux.nom.clone();
+        affichans.push(Affichan::new(affichan, Box::new(move |ecrit: &Ecrit| {
+            ecrit.flux.as_ref() == Some(&nom_flux) && (ecrit.status == Status::Ouvert || ecrit.status == Status::OuvertPlus)
+        })));
+    });
+    let salon_log = config.salon_log.get();
+    config::init(config);
+
+    match ecrit::quarantaine::preparer_base(CHEMIN_BASE) {
+        Ok(Some(resume)) => {
+            eprintln!("{resume}");
+            let _ = RESUME_CHARGEMENT.set(resume);
+        }
+        Ok(None) => (),
+        Err(e) => panic!("Erreur lors de la préparation de la base de données : {e}")
+    }
+
+    if let Err(e) = ecrit::statistiques::charger() {
+        panic!("Erreur lors du chargement des statistiques : {e}");
+    }
+
+    if let Err(e) = ecrit::auteurs::charger() {
+        panic!("Erreur lors du chargement du registre des auteurs : {e}");
+    }
+
+    if let Some(chemin) = &config::get().sqlite {
+        #[cfg(feature = "sqlite")]
+        match ecrit::sqlite::ouvrir(chemin, CHEMIN_BASE) {
+            Ok(0) => (),
+            Ok(nombre) => println!("{nombre} écrit(s) importé(s) de {CHEMIN_BASE} dans {chemin}."),
+            Err(e) => panic!("Erreur lors de l’ouverture de la base SQLite {chemin} : {e}")
+        }
+        #[cfg(not(feature = "sqlite"))]
+        eprintln!("Base SQLite {chemin} ignorée : le bot n’a pas été compilé avec la fonctionnalité sqlite.");
+    }
+
+    /* Le token est lu en priorité dans l’environnement pour éviter qu’il apparaisse dans la liste des processus. */
+    let token = env::var("CRITIBOT_TOKEN").ok().or(args.get(1).cloned());
 
-    if let Some(token) = args.get(1) {
+    if let Some(token) = token {
         match Bot::default()
             .owners(owners)
             .command_checker(Box::new(command_checker))
             .event_handler(event_handler)
-            .set_log(725708994915860510)
+            .set_log(salon_log)
             .setup(
-            token.clone(),
+            token,
             GatewayIntents::GUILD_MESSAGES | GatewayIntents::GUILD_MEMBERS,
-            "./critibot.yml",
+            CHEMIN_BASE,
             commands::command_list(),
-            vec![
-                Affichan::new(ChannelId::new(1299620421506699275), Box::new(|ecrit| {
-                    ecrit.status == Status::Ouvert || ecrit.status == Status::OuvertPlus
-                })),
-                Affichan::new(ChannelId::new(896361827884220467), Box::new(|ecrit| {
-                    ecrit.status == Status::Inconnu || ecrit.status == Status::Infraction
-                })),
-                Affichan::new(ChannelId::new(896362452818747412), Box::new(|ecrit| {
-                    ecrit.type_ == Type::Autre
-                })),
-            ],
+            affichans,
             HashMap::new()
         ).await {
-            Ok(mut bot) => if let Err(e) = bot.start().await {
-                panic!("Erreur lors de l’exécution du bot: {e}");
+            Ok(mut bot) => {
+                /* La base est chargée : la version de son format, retirée par preparer_base, est remise. */
+                if let Err(e) = ecrit::migrations::inscrire_version(CHEMIN_BASE) {
+                    eprintln!("Impossible d’inscrire la version de la base de données : {e}");
+                }
+                if let Err(e) = bot.start().await {
+                    panic!("Erreur lors de l’exécution du bot: {e}");
+                }
             }
             Err(e) => panic!("Erreur lors du chargement du bot: {e}")
         }
+    } else {
+        panic!("Aucun token fourni : définissez CRITIBOT_TOKEN ou passez-le en premier argument.");
     }
 }
 
@@ -92,7 +189,7 @@ async fn main() {
 /// Wikidot dessus pour pouvoir lier les deux.
 async fn thread_created(ctx: &serenity::Context, data: &DataType, thread: &GuildChannel) -> Result<bool, ErrType> {
     match thread.parent_id {
-        Some(parent_id) if parent_id.get() == 1299603184519479357 => (),
+        Some(parent_id) if parent_id == config::get().forum_critiques => (),
         _ => return Ok(true)
     };
 
@@ -109,7 +206,7 @@ async fn thread_created(ctx: &serenity::Context, data: &DataType, thread: &Guild
         None => return Ok(true)
     };
 
-    Ecrit::maj_rss(data).await?;
+    Ecrit::maj_flux(data).await?;
 
     let mut bot = data.lock().await;
     let ecrit = match bot.database.get_mut(&ecrit_id) {
@@ -117,8 +214,9 @@ async fn thread_created(ctx: &serenity::Context, data: &DataType, thread: &Guild
         None => return Ok(true)
     };
 
-    ecrit.discord_chan = Some(thread.id);
-    ecrit.modified = true;
+    let ancien = ecrit.discord_chan.replace(thread.id);
+    ecrit.historiser(&Acteur::Critibot, Action::LienDiscord,
+                     ancien.map(|chan| format!("<#{chan}>")), Some(format!("<#{}>", thread.id)));
     bot.update_affichans = true;
 
     message.react(ctx, ReactionType::Unicode("👌".to_string())).await?;
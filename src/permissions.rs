@@ -0,0 +1,29 @@
+use fondabots_lib::command_data::Permission;
+use poise::serenity_prelude as serenity;
+use serenity::all::RoleId;
+
+use crate::config;
+
+/// Indique si un membre possédant les rôles donnés dispose de la permission demandée.
+pub fn accorde(roles_membre: &[RoleId], permission: Permission) -> bool {
+    let roles = &config::get().roles;
+    let par_permission = match permission {
+        Permission::READ | Permission::NONE => true,
+        Permission::WRITE => roles_membre.contains(&roles.membre), /* Classe-C membre */
+        Permission::MANAGE => roles_membre.contains(&roles.equipe_critique) /* Équipe Critique */
+    };
+    par_permission || roles_membre.contains(&roles.staff) /* Staff */
+}
+
+/// Permission nécessaire pour utiliser un bouton, d’après le type et l’action de son identifiant.
+pub fn bouton(type_: &str, action: Option<&str>) -> Permission {
+    match (type_, action) {
+        ("e" | "ok", Some("r")) => Permission::MANAGE, /* Refusé */
+        ("e" | "ok", _) | ("tm", _) | ("an", _) => Permission::WRITE,
+        /* Rappel de marque, envoyé en message privé à l’auteur de la marque lui-même. */
+        ("rm", _) | ("no", _) => Permission::NONE,
+        /* Tableau des marques : le membre n’y agit que sur ses propres marques. */
+        ("mm", _) | ("ml", _) => Permission::NONE,
+        _ => Permission::READ
+    }
+}